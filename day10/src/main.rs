@@ -1,6 +1,8 @@
 use std::env;
+use std::error::Error;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::io::{BufRead, BufReader};
+use std::ops::RangeInclusive;
 
 fn joltage_difference(x: u64, y: u64) -> (u64, u64, u64) {
     let (mut a, mut b, mut c) = (0, 0, 0);
@@ -11,7 +13,7 @@ fn joltage_difference(x: u64, y: u64) -> (u64, u64, u64) {
         3 => c += 1,
         _ => panic!("too much joltage"),
     }
-    return (a, b, c);
+    (a, b, c)
 }
 
 fn joltage_chain(list: &[u64]) -> (u64, u64, u64) {
@@ -22,45 +24,43 @@ fn joltage_chain(list: &[u64]) -> (u64, u64, u64) {
         b += y;
         c += z;
     }
-    return (a, b, c);
+    (a, b, c)
 }
 
-fn max_joltage_arragements(list: &[u64]) -> u64 {
-    let mut slices = Vec::new();
-    let mut current = Vec::new();
-    for window in list.windows(2) {
-        match window[1] - window[0] {
-            1 => current.push(window[0]),
-            3 => {
-                current.push(window[0]);
-                slices.push(current);
-                current = Vec::new();
+// ways[i] is how many chains from the outlet reach adapter i, each adapter adds the
+// ways of every earlier adapter it can be plugged into, None if the count overflows
+fn count_arrangements(list: &[u64], gaps: RangeInclusive<u64>) -> Option<u128> {
+    if list.is_empty() {
+        return Some(0);
+    }
+    let mut ways = vec![0u128; list.len()];
+    ways[0] = 1;
+    for i in 1..list.len() {
+        let mut total = 0u128;
+        for j in (0..i).rev() {
+            let gap = list[i] - list[j];
+            if gap > *gaps.end() {
+                break;
+            }
+            if gap >= *gaps.start() {
+                total = total.checked_add(ways[j])?;
             }
-            _ => {}
         }
+        ways[i] = total;
     }
-    return slices
-        .iter()
-        .map(|slice| match slice.len() {
-            1 => 1,
-            2 => 1,
-            3 => 2,
-            4 => 4,
-            5 => 7,
-            _ => panic!("unexpected slice size"),
-        })
-        .product();
+    ways.last().copied()
 }
 
-fn create_adapter_list(list: &[u64]) -> Vec<u64> {
+// the outlet at 0, the adapters in order and the device device_gap above the highest adapter
+fn create_adapter_list(list: &[u64], device_gap: u64) -> Vec<u64> {
     let mut list = Vec::from(list);
-    list.sort();
+    list.sort_unstable();
     list.insert(0, 0);
-    list.push(list.last().unwrap() + 3);
-    return Vec::from(list);
+    list.push(list.last().unwrap() + device_gap);
+    list
 }
 
-fn main() -> Result<(), io::Error> {
+fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = env::args().collect();
     let reader = BufReader::new(File::open(&args[1]).expect("File::open failed"));
     let list = reader
@@ -72,12 +72,21 @@ fn main() -> Result<(), io::Error> {
         })
         .collect::<Vec<u64>>();
 
-    let list = create_adapter_list(&list);
     if &args[2] == "1" {
+        let list = create_adapter_list(&list, 3);
         let (a, _, c) = joltage_chain(&list);
         println!("{:?}", a * c);
     } else if &args[2] == "2" {
-        println!("{}", (max_joltage_arragements(&list)));
+        // 2 [min gap] [max gap]
+        let min = args.get(3).map_or(Ok(1), |n| n.parse::<u64>())?;
+        let max = args.get(4).map_or(Ok(3), |n| n.parse::<u64>())?;
+        if min > max {
+            return Err(format!("empty gap range {}..={}", min, max).into());
+        }
+        let list = create_adapter_list(&list, max);
+        let count = count_arrangements(&list, min..=max)
+            .ok_or("the number of arrangements does not fit in a u128")?;
+        println!("{}", count);
     }
 
     Ok(())
@@ -107,16 +116,44 @@ mod tests {
     }
     #[test]
     fn test_chain() {
-        let list = create_adapter_list(&[16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4]);
+        let list = create_adapter_list(&[16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4], 3);
         assert_eq!(joltage_chain(&list), (7, 0, 5));
 
-        assert_eq!(max_joltage_arragements(&list), 8);
-        let list = create_adapter_list(&[
-            28, 33, 18, 42, 31, 14, 46, 20, 48, 47, 24, 23, 49, 45, 19, 38, 39, 11, 1, 32, 25, 35,
-            8, 17, 7, 9, 4, 2, 34, 10, 3,
-        ]);
+        assert_eq!(count_arrangements(&list, 1..=3), Some(8));
+        let list = create_adapter_list(
+            &[
+                28, 33, 18, 42, 31, 14, 46, 20, 48, 47, 24, 23, 49, 45, 19, 38, 39, 11, 1, 32, 25,
+                35, 8, 17, 7, 9, 4, 2, 34, 10, 3,
+            ],
+            3,
+        );
         assert_eq!(joltage_chain(&list), (22, 0, 10));
 
-        assert_eq!(max_joltage_arragements(&list), 19208);
+        assert_eq!(count_arrangements(&list, 1..=3), Some(19208));
+    }
+
+    #[test]
+    fn test_arrangements() {
+        // 0 1 3 4 7 has a gap of 2 and three chains
+        let list = create_adapter_list(&[1, 3, 4], 3);
+        assert_eq!(count_arrangements(&list, 1..=3), Some(3));
+        assert_eq!(count_arrangements(&list, 1..=1), Some(0));
+        assert_eq!(count_arrangements(&list, 2..=3), Some(0));
+        // a run of 10 adapters one apart
+        let list = create_adapter_list(&(1..=10).collect::<Vec<u64>>(), 3);
+        assert_eq!(count_arrangements(&list, 1..=3), Some(274));
+        assert_eq!(count_arrangements(&list, 1..=4), Some(609));
+        assert_eq!(count_arrangements(&[], 1..=3), Some(0));
+    }
+
+    #[test]
+    fn test_arrangements_overflow() {
+        let list = create_adapter_list(&(1..=100).collect::<Vec<u64>>(), 3);
+        assert_eq!(
+            count_arrangements(&list, 1..=3),
+            Some(180396380815100901214157639)
+        );
+        let list = create_adapter_list(&(1..=150).collect::<Vec<u64>>(), 3);
+        assert_eq!(count_arrangements(&list, 1..=3), None);
     }
 }
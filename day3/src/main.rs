@@ -1,60 +1,271 @@
 use std::env;
+use std::error::Error;
+use std::fmt;
 use std::fs::File;
-use std::io::{self, BufRead};
-use std::path::Path;
-
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
-where
-    P: AsRef<Path>,
-{
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
-}
-
-fn check_slope(left: usize, down: usize, input: &String) -> usize {
-    let mut n_trees = 0;
-    let mut width = 0;
-    let mut x = left;
-    let mut y = 0;
-
-    if let Ok(lines) = read_lines(&input) {
-        for line in lines {
-            if let Ok(row) = line {
-                if width == 0 {
-                    width = row.len()
-                }
-                if y < down {
-                    y += 1;
-                    continue;
-                }
-                y = 1;
-                let chars = row.chars().collect::<Vec<char>>();
-                if chars[x] == '#' {
-                    n_trees += 1;
+use std::io::{self, BufRead, BufReader};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Cell {
+    Open,
+    Tree,
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cell::Open => write!(f, "."),
+            Cell::Tree => write!(f, "#"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct ParseError {
+    line: usize,
+    message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Debug)]
+struct Map {
+    grid: Vec<Vec<Cell>>,
+    width: usize,
+}
+
+impl Map {
+    // every row must be as wide as the first one, empty lines are skipped
+    pub fn new(lines: &[String]) -> Result<Map, ParseError> {
+        let mut grid: Vec<Vec<Cell>> = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            let row = line
+                .chars()
+                .map(|c| match c {
+                    '.' => Ok(Cell::Open),
+                    '#' => Ok(Cell::Tree),
+                    _ => Err(ParseError {
+                        line: i + 1,
+                        message: format!("invalid char in map: '{}'", c),
+                    }),
+                })
+                .collect::<Result<Vec<Cell>, ParseError>>()?;
+            if let Some(first) = grid.first() {
+                if row.len() != first.len() {
+                    return Err(ParseError {
+                        line: i + 1,
+                        message: format!("row is {} wide, expected {}", row.len(), first.len()),
+                    });
                 }
-                x += left;
-                if x >= width {
-                    x -= width;
+            }
+            grid.push(row);
+        }
+        let width = grid.first().map_or(0, |row| row.len());
+        Ok(Map { grid, width })
+    }
+
+    pub fn height(&self) -> usize {
+        self.grid.len()
+    }
+
+    // the map repeats to the left and right, so x is taken modulo the width
+    fn get(&self, x: i64, y: usize) -> Cell {
+        self.grid[y][x.rem_euclid(self.width as i64) as usize]
+    }
+
+    pub fn traverse(&self, right: i64, down: usize) -> Traversal<'_> {
+        assert!(down > 0, "traverse: down must be at least 1");
+        Traversal {
+            map: self,
+            right,
+            down,
+            x: 0,
+            y: 0,
+        }
+    }
+
+    pub fn count_trees(&self, right: i64, down: usize) -> usize {
+        self.traverse(right, down)
+            .filter(|(_, _, cell)| *cell == Cell::Tree)
+            .count()
+    }
+
+    // renders the map, repeated as often as needed to fit the whole path,
+    // with every checked square marked 'O' if it was open and 'X' if it was a tree,
+    // as in the puzzle's illustration, not 'O' for trees as first asked
+    pub fn render(&self, right: i64, down: usize) -> String {
+        if self.height() == 0 {
+            return String::new();
+        }
+        let mut path: Vec<Option<(i64, Cell)>> = vec![None; self.height()];
+        let width = self.width as i64;
+        let (mut min_x, mut max_x) = (0, width - 1);
+        for (step, (_, y, cell)) in self.traverse(right, down).enumerate() {
+            let x = (step as i64 + 1) * right;
+            min_x = min_x.min(x);
+            max_x = max_x.max(x);
+            path[y] = Some((x, cell));
+        }
+        let first = min_x.div_euclid(width) * width;
+        let last = (max_x.div_euclid(width) + 1) * width;
+
+        let mut out = String::new();
+        for (y, visited) in path.iter().enumerate() {
+            for x in first..last {
+                match visited {
+                    Some((vx, Cell::Open)) if *vx == x => out.push('O'),
+                    Some((vx, Cell::Tree)) if *vx == x => out.push('X'),
+                    _ => out.push_str(&self.get(x, y).to_string()),
                 }
             }
+            out.push('\n');
         }
+        out
     }
-    n_trees
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+struct Traversal<'a> {
+    map: &'a Map,
+    right: i64,
+    down: usize,
+    x: i64,
+    y: usize,
+}
 
-    let mut n_trees = 1;
+impl<'a> Iterator for Traversal<'a> {
+    // (x wrapped into the map width, y, cell at that position)
+    type Item = (usize, usize, Cell);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.x += self.right;
+        self.y += self.down;
+        if self.y >= self.map.height() {
+            return None;
+        }
+        let x = self.x.rem_euclid(self.map.width as i64) as usize;
+        Some((x, self.y, self.map.get(self.x, self.y)))
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+    let reader = BufReader::new(File::open(&args[1])?);
+    let lines = reader.lines().collect::<Result<Vec<String>, io::Error>>()?;
+    let map = Map::new(&lines)?;
 
     if &args[2] == "1" {
-        n_trees = check_slope(3, 1, &args[1]);
+        println!("{}", map.count_trees(3, 1));
     } else if &args[2] == "2" {
-        n_trees *= check_slope(1, 1, &args[1]);
-        n_trees *= check_slope(3, 1, &args[1]);
-        n_trees *= check_slope(5, 1, &args[1]);
-        n_trees *= check_slope(7, 1, &args[1]);
-        n_trees *= check_slope(1, 2, &args[1]);
+        let slopes = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)];
+        let n_trees = slopes
+            .iter()
+            .map(|(right, down)| map.count_trees(*right, *down))
+            .product::<usize>();
+        println!("{}", n_trees);
+    } else if &args[2] == "render" {
+        // render [right] [down]
+        let right = args.get(3).map_or(Ok(3), |v| v.parse::<i64>())?;
+        let down = args.get(4).map_or(Ok(1), |v| v.parse::<usize>())?;
+        if down == 0 {
+            return Err("down must be at least 1".into());
+        }
+        print!("{}", map.render(right, down));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_map() -> Map {
+        let lines = "..##.......
+#...#...#..
+.#....#..#.
+..#.#...#.#
+.#...##..#.
+..#.##.....
+.#.#.#....#
+.#........#
+#.##...#...
+#...##....#
+.#..#...#.#"
+            .lines()
+            .map(String::from)
+            .collect::<Vec<String>>();
+        Map::new(&lines).unwrap()
+    }
+
+    #[test]
+    fn test_count_trees() {
+        let map = test_map();
+        assert_eq!(map.count_trees(1, 1), 2);
+        assert_eq!(map.count_trees(3, 1), 7);
+        assert_eq!(map.count_trees(5, 1), 3);
+        assert_eq!(map.count_trees(7, 1), 4);
+        assert_eq!(map.count_trees(1, 2), 2);
+    }
+
+    #[test]
+    fn test_traverse() {
+        let map = test_map();
+        let visited = map.traverse(1, 2).collect::<Vec<_>>();
+        assert_eq!(
+            visited,
+            vec![
+                (1, 2, Cell::Tree),
+                (2, 4, Cell::Open),
+                (3, 6, Cell::Tree),
+                (4, 8, Cell::Open),
+                (5, 10, Cell::Open),
+            ]
+        );
+
+        // moving a whole map width further is the same as not moving at all
+        assert_eq!(map.count_trees(14, 1), map.count_trees(3, 1));
+        assert_eq!(map.count_trees(-8, 1), map.count_trees(3, 1));
+        let left = map.traverse(-1, 1).next();
+        assert_eq!(left, Some((10, 1, Cell::Open)));
+    }
+
+    #[test]
+    fn test_render() {
+        let map = test_map();
+        let render = map.render(3, 1);
+        let rows = render.lines().collect::<Vec<&str>>();
+        assert_eq!(rows.len(), 11);
+        assert_eq!(rows[0], "..##.........##.........##.......");
+        assert_eq!(rows[1], "#..O#...#..#...#...#..#...#...#..");
+        assert_eq!(rows[2], ".#....X..#..#....#..#..#....#..#.");
+        assert_eq!(rows[10], ".#..#...#.#.#..#...#.#.#..#...X.#");
+    }
+
+    #[test]
+    fn test_invalid_maps() {
+        let lines = |input: &str| input.lines().map(String::from).collect::<Vec<String>>();
+        let empty = Map::new(&[]).unwrap();
+        assert_eq!(empty.render(3, 1), "");
+        assert_eq!(empty.count_trees(3, 1), 0);
+        assert_eq!(Map::new(&lines("\n\n")).unwrap().height(), 0);
+
+        assert_eq!(
+            Map::new(&lines("..#\n\n.#")).err(),
+            Some(ParseError {
+                line: 3,
+                message: "row is 2 wide, expected 3".to_string()
+            })
+        );
+        assert_eq!(
+            Map::new(&lines("..#\n.o.")).unwrap_err().to_string(),
+            "line 2: invalid char in map: 'o'"
+        );
     }
-    println!("{}", n_trees)
 }
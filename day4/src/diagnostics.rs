@@ -0,0 +1,112 @@
+use crate::passport::Record;
+use crate::schema::{Schema, Violation};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub end_line: usize,
+    pub valid: bool,
+    pub violations: Vec<Violation>,
+    pub warnings: Vec<String>,
+}
+
+pub fn diagnose(records: &[Record], schema: &Schema) -> Vec<Diagnostic> {
+    records
+        .iter()
+        .map(|record| {
+            let violations = record.passport.violations(schema);
+            let mut warnings = record
+                .warnings
+                .iter()
+                .map(|w| w.to_string())
+                .collect::<Vec<String>>();
+            for key in record.passport.unknown_fields(schema) {
+                warnings.push(format!("unknown field '{}'", key));
+            }
+            Diagnostic {
+                line: record.start,
+                end_line: record.end,
+                valid: violations.is_empty(),
+                violations,
+                warnings,
+            }
+        })
+        .collect()
+}
+
+pub fn render_text(diagnostics: &[Diagnostic]) -> String {
+    let mut out = String::new();
+    for diagnostic in diagnostics {
+        match diagnostic.valid {
+            true => out.push_str(&format!("line {}: valid\n", diagnostic.line)),
+            false => out.push_str(&format!("line {}: invalid\n", diagnostic.line)),
+        }
+        for violation in &diagnostic.violations {
+            out.push_str(&format!("  {}\n", violation));
+        }
+        for warning in &diagnostic.warnings {
+            out.push_str(&format!("  warning: {}\n", warning));
+        }
+    }
+    let n_valid = diagnostics.iter().filter(|d| d.valid).count();
+    out.push_str(&format!(
+        "{} of {} passports valid\n",
+        n_valid,
+        diagnostics.len()
+    ));
+    out
+}
+
+pub fn render_json(diagnostics: &[Diagnostic]) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::passport::parse_records;
+
+    fn diagnostics() -> Vec<Diagnostic> {
+        let batch = "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd byr:1937 iyr:2017
+
+iyr:2013 ecl:amb eyr:2023 pid:028048884
+hcl:#cfa07d byr:1929
+
+ecl:gry pid:860033327 eyr:2020 hcl:#fffffd byr:1937 iyr:2017 hgt:190in
+foo:bar";
+        let records = parse_records(batch.as_bytes()).unwrap();
+        diagnose(&records, &Schema::default())
+    }
+
+    #[test]
+    fn test_render_text() {
+        assert_eq!(
+            render_text(&diagnostics()),
+            "line 1: invalid
+  missing hgt
+line 3: invalid
+  missing hgt
+line 6: invalid
+  hgt=190in: in must be 59..=76
+  warning: unknown field 'foo'
+0 of 3 passports valid
+"
+        );
+    }
+
+    #[test]
+    fn test_render_json() {
+        let json = render_json(&diagnostics()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value[2]["line"], 6);
+        assert_eq!(value[2]["end_line"], 7);
+        assert_eq!(value[2]["valid"], false);
+        assert_eq!(value[2]["violations"][0]["kind"], "invalid");
+        assert_eq!(value[2]["violations"][0]["field"], "hgt");
+        assert_eq!(value[2]["violations"][0]["value"], "190in");
+        assert_eq!(value[2]["violations"][0]["reason"], "in must be 59..=76");
+        assert_eq!(value[2]["warnings"][0], "unknown field 'foo'");
+        assert_eq!(value[0]["violations"][0]["kind"], "missing");
+    }
+}
@@ -0,0 +1,533 @@
+use crate::passport::{parse_records, Passport, Record};
+use crate::schema::Schema;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    // blank-line separated records, as in the puzzle input
+    Batch,
+    // one passport per line
+    Lines,
+    Csv,
+    JsonLines,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "batch" => Ok(Format::Batch),
+            "lines" => Ok(Format::Lines),
+            "csv" => Ok(Format::Csv),
+            "jsonl" => Ok(Format::JsonLines),
+            _ => Err(format!(
+                "unknown format '{}', expected batch, lines, csv or jsonl",
+                s
+            )),
+        }
+    }
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => Format::Csv,
+            Some("jsonl") | Some("json") => Format::JsonLines,
+            _ => Format::Batch,
+        }
+    }
+}
+
+fn invalid_data(line: usize, message: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line, message),
+    )
+}
+
+pub fn read<R: BufRead>(reader: R, format: Format) -> io::Result<Vec<Record>> {
+    match format {
+        Format::Batch => parse_records(reader),
+        Format::Lines => read_lines(reader),
+        Format::Csv => read_csv(reader),
+        Format::JsonLines => read_jsonl(reader),
+    }
+}
+
+fn read_lines<R: BufRead>(reader: R) -> io::Result<Vec<Record>> {
+    let mut records = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut record = Record::new(i + 1);
+        record.push_line(i + 1, &line);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+// splits one csv row; None marks an unquoted empty cell, which stands for a missing field
+fn split_csv_row(line: &str, line_number: usize) -> io::Result<Vec<Option<String>>> {
+    let mut cells = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        let mut cell = String::new();
+        let mut quoted = false;
+        if chars.peek() == Some(&'"') {
+            quoted = true;
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        cell.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => cell.push(c),
+                    None => return Err(invalid_data(line_number, "unterminated quote".into())),
+                }
+            }
+        }
+        while let Some(c) = chars.peek() {
+            if *c == ',' {
+                break;
+            }
+            if quoted {
+                return Err(invalid_data(
+                    line_number,
+                    "unexpected text after closing quote".into(),
+                ));
+            }
+            cell.push(*c);
+            chars.next();
+        }
+        cells.push(match quoted || !cell.is_empty() {
+            true => Some(cell),
+            false => None,
+        });
+        if chars.next().is_none() {
+            return Ok(cells);
+        }
+    }
+}
+
+fn read_csv<R: BufRead>(reader: R) -> io::Result<Vec<Record>> {
+    let mut lines = reader.lines().enumerate();
+    let header = match lines.next() {
+        // an empty batch is written as an empty header
+        Some((_, line)) => match line? {
+            line if line.is_empty() => Vec::new(),
+            line => split_csv_row(&line, 1)?,
+        },
+        None => return Ok(Vec::new()),
+    };
+    let header = header
+        .into_iter()
+        .map(|cell| cell.ok_or_else(|| invalid_data(1, "empty column name".into())))
+        .collect::<io::Result<Vec<String>>>()?;
+
+    let mut records = Vec::new();
+    for (i, line) in lines {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let cells = split_csv_row(&line, i + 1)?;
+        if cells.len() != header.len() {
+            return Err(invalid_data(
+                i + 1,
+                format!("expected {} columns, got {}", header.len(), cells.len()),
+            ));
+        }
+        let mut record = Record::new(i + 1);
+        for (key, cell) in header.iter().zip(cells) {
+            if let Some(value) = cell {
+                record.passport.fields.push((key.clone(), value));
+            }
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn read_jsonl<R: BufRead>(reader: R) -> io::Result<Vec<Record>> {
+    let mut records = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let object: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(&line).map_err(|e| invalid_data(i + 1, e.to_string()))?;
+        let mut record = Record::new(i + 1);
+        for (key, value) in object {
+            match value {
+                serde_json::Value::String(value) => record.passport.fields.push((key, value)),
+                _ => {
+                    return Err(invalid_data(
+                        i + 1,
+                        format!("value of '{}' is not a string", key),
+                    ))
+                }
+            }
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
+
+// schema fields first, in schema order, then unknown fields in input order.
+// repeated keys keep every value, in input order
+pub fn canonical(passport: &Passport, schema: &Schema) -> Passport {
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for field in &schema.fields {
+        fields.extend(
+            passport
+                .fields
+                .iter()
+                .filter(|(key, _)| *key == field.name)
+                .cloned(),
+        );
+    }
+    let unknown = passport.unknown_fields(schema);
+    fields.extend(
+        passport
+            .fields
+            .iter()
+            .filter(|(key, _)| unknown.contains(&key.as_str()))
+            .cloned(),
+    );
+    Passport { fields }
+}
+
+// why a field can not be written in format and read back unchanged, if it can not
+fn unwritable(passport: &Passport, index: usize, format: Format) -> Option<String> {
+    let (key, value) = &passport.fields[index];
+    let repeated = passport.fields[..index].iter().any(|(k, _)| k == key);
+    let line_break = |s: &str| s.contains(['\n', '\r']);
+    match format {
+        Format::Batch | Format::Lines => {
+            if key.is_empty() || key.contains(|c: char| c == ':' || c.is_whitespace()) {
+                Some(format!("key '{}' can not be written as key:value", key))
+            } else if value.contains(char::is_whitespace) {
+                Some(format!("value of '{}' contains whitespace", key))
+            } else {
+                None
+            }
+        }
+        Format::Csv if line_break(key) || line_break(value) => {
+            Some(format!("field '{}' contains a line break", key))
+        }
+        Format::Csv | Format::JsonLines if repeated => Some(format!("key '{}' is repeated", key)),
+        _ => None,
+    }
+}
+
+fn passport_line(passport: &Passport) -> String {
+    passport
+        .fields
+        .iter()
+        .map(|(key, value)| format!("{}:{}", key, value))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn csv_cell(value: &str) -> String {
+    if value.is_empty() || value.contains([',', '"']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// fails with InvalidData before writing anything if a field could not be read back
+pub fn write<W: Write>(out: &mut W, passports: &[Passport], format: Format) -> io::Result<()> {
+    for (i, passport) in passports.iter().enumerate() {
+        // an empty line or row is skipped when reading
+        if passport.fields.is_empty() && format != Format::JsonLines {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("passport {}: no fields", i + 1),
+            ));
+        }
+        for index in 0..passport.fields.len() {
+            if let Some(message) = unwritable(passport, index, format) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("passport {}: {}", i + 1, message),
+                ));
+            }
+        }
+    }
+    match format {
+        Format::Batch => {
+            let blocks = passports.iter().map(passport_line).collect::<Vec<String>>();
+            if !blocks.is_empty() {
+                writeln!(out, "{}", blocks.join("\n\n"))?;
+            }
+        }
+        Format::Lines => {
+            for passport in passports {
+                writeln!(out, "{}", passport_line(passport))?;
+            }
+        }
+        Format::Csv => {
+            let mut columns: Vec<&str> = Vec::new();
+            for passport in passports {
+                for (key, _) in &passport.fields {
+                    if !columns.contains(&key.as_str()) {
+                        columns.push(key);
+                    }
+                }
+            }
+            let header = columns.iter().map(|c| csv_cell(c)).collect::<Vec<_>>();
+            writeln!(out, "{}", header.join(","))?;
+            for passport in passports {
+                let row = columns
+                    .iter()
+                    .map(|column| passport.get(column).map_or(String::new(), csv_cell))
+                    .collect::<Vec<String>>();
+                writeln!(out, "{}", row.join(","))?;
+            }
+        }
+        Format::JsonLines => {
+            for passport in passports {
+                let object = passport
+                    .fields
+                    .iter()
+                    .map(|(key, value)| (key.clone(), serde_json::Value::from(value.as_str())))
+                    .collect::<serde_json::Map<String, serde_json::Value>>();
+                writeln!(out, "{}", serde_json::Value::Object(object))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+struct Options {
+    from: Option<Format>,
+    to: Format,
+    drop_invalid: bool,
+    schema: Option<String>,
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        from: None,
+        to: Format::Lines,
+        drop_invalid: false,
+        schema: None,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--from" => options.from = Some(value()?.parse()?),
+            "--to" => options.to = value()?.parse()?,
+            "--schema" => options.schema = Some(value()?.clone()),
+            "--drop-invalid" => options.drop_invalid = true,
+            _ => return Err(format!("unknown format option '{}'", arg)),
+        }
+    }
+    Ok(options)
+}
+
+// day4 <file> format [--from FORMAT] [--to FORMAT] [--drop-invalid] [--schema FILE]
+pub fn run(path: &str, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let options = parse_options(args)?;
+    let schema = match &options.schema {
+        Some(path) => Schema::load(path)?,
+        None => Schema::default(),
+    };
+    let from = options
+        .from
+        .unwrap_or_else(|| Format::from_path(Path::new(path)));
+    let records = read(BufReader::new(File::open(path)?), from)?;
+
+    let passports = records
+        .iter()
+        .filter(|record| !options.drop_invalid || record.passport.is_valid_part2(&schema))
+        .map(|record| canonical(&record.passport, &schema))
+        .collect::<Vec<Passport>>();
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    write(&mut out, &passports, options.to)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BATCH: &str = "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd
+byr:1937 iyr:2017 cid:147 hgt:183cm
+
+iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884
+hcl:#cfa07d byr:1929 note:a,\"b\" empty:
+
+hcl:#ae17e1 iyr:2013
+eyr:2024
+ecl:brn pid:760753108 byr:1931
+hgt:179cm";
+
+    fn convert(input: &str, from: Format, to: Format) -> String {
+        let schema = Schema::default();
+        let passports = read(input.as_bytes(), from)
+            .unwrap()
+            .iter()
+            .map(|record| canonical(&record.passport, &schema))
+            .collect::<Vec<Passport>>();
+        let mut out = Vec::new();
+        write(&mut out, &passports, to).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_canonical_lines() {
+        assert_eq!(
+            convert(BATCH, Format::Batch, Format::Lines),
+            "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327 cid:147
+byr:1929 iyr:2013 eyr:2023 hcl:#cfa07d ecl:amb pid:028048884 cid:350 note:a,\"b\" empty:
+byr:1931 iyr:2013 eyr:2024 hgt:179cm hcl:#ae17e1 ecl:brn pid:760753108
+"
+        );
+    }
+
+    #[test]
+    fn test_csv() {
+        let csv = convert(BATCH, Format::Batch, Format::Csv);
+        assert_eq!(
+            csv.lines().next(),
+            Some("byr,iyr,eyr,hgt,hcl,ecl,pid,cid,note,empty")
+        );
+        assert_eq!(
+            csv.lines().nth(2),
+            Some("1929,2013,2023,,#cfa07d,amb,028048884,350,\"a,\"\"b\"\"\",\"\"")
+        );
+    }
+
+    fn try_convert(input: &str, from: Format, to: Format) -> io::Result<String> {
+        let passports = read(input.as_bytes(), from)?
+            .iter()
+            .map(|record| canonical(&record.passport, &Schema::default()))
+            .collect::<Vec<Passport>>();
+        let mut out = Vec::new();
+        write(&mut out, &passports, to)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let lines = convert(BATCH, Format::Batch, Format::Lines);
+        for format in &[Format::Batch, Format::Lines, Format::Csv, Format::JsonLines] {
+            let converted = convert(&lines, Format::Lines, *format);
+            assert_eq!(convert(&converted, *format, Format::Lines), lines);
+        }
+
+        // whitespace in a csv cell has no key:value form
+        let csv = "byr,note\n1937,a b\n";
+        assert_eq!(convert(csv, Format::Csv, Format::Csv), csv);
+        assert_eq!(
+            convert(csv, Format::Csv, Format::JsonLines).trim(),
+            r#"{"byr":"1937","note":"a b"}"#
+        );
+        for format in &[Format::Batch, Format::Lines] {
+            let error = try_convert(csv, Format::Csv, *format).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(
+                error.to_string(),
+                "passport 1: value of 'note' contains whitespace"
+            );
+        }
+        let jsonl = r#"{"byr":"1937","a:b":"1"}"#;
+        assert_eq!(
+            try_convert(jsonl, Format::JsonLines, Format::Lines)
+                .unwrap_err()
+                .to_string(),
+            "passport 1: key 'a:b' can not be written as key:value"
+        );
+        let jsonl = r#"{"byr":"1937","note":"a\nb"}"#;
+        assert_eq!(
+            try_convert(jsonl, Format::JsonLines, Format::Csv)
+                .unwrap_err()
+                .to_string(),
+            "passport 1: field 'note' contains a line break"
+        );
+
+        // a record without fields would be read back as nothing
+        let csv = "byr,iyr\n,\n1937,2017\n";
+        assert_eq!(
+            convert(csv, Format::Csv, Format::JsonLines),
+            "{}\n{\"byr\":\"1937\",\"iyr\":\"2017\"}\n"
+        );
+        for format in &[Format::Batch, Format::Lines, Format::Csv] {
+            assert_eq!(
+                try_convert(csv, Format::Csv, *format)
+                    .unwrap_err()
+                    .to_string(),
+                "passport 1: no fields"
+            );
+        }
+        // an empty batch
+        for format in &[Format::Batch, Format::Lines, Format::Csv, Format::JsonLines] {
+            let converted = convert("", Format::Batch, *format);
+            assert_eq!(convert(&converted, *format, Format::Lines), "");
+        }
+        assert_eq!(convert("", Format::Batch, Format::Csv), "\n");
+        assert!(read("\n1937\n".as_bytes(), Format::Csv).is_err());
+
+        // repeated keys survive the formats that can hold them
+        let repeated = "byr:1 byr:2 x:3\n";
+        assert_eq!(convert(repeated, Format::Lines, Format::Lines), repeated);
+        assert_eq!(
+            convert(
+                &convert(repeated, Format::Lines, Format::Batch),
+                Format::Batch,
+                Format::Lines
+            ),
+            repeated
+        );
+        for format in &[Format::Csv, Format::JsonLines] {
+            assert_eq!(
+                try_convert(repeated, Format::Lines, *format)
+                    .unwrap_err()
+                    .to_string(),
+                "passport 1: key 'byr' is repeated"
+            );
+        }
+    }
+
+    #[test]
+    fn test_jsonl() {
+        let jsonl = convert(BATCH, Format::Batch, Format::JsonLines);
+        assert_eq!(
+            jsonl.lines().last(),
+            Some(
+                r##"{"byr":"1931","iyr":"2013","eyr":"2024","hgt":"179cm","hcl":"#ae17e1","ecl":"brn","pid":"760753108"}"##
+            )
+        );
+        assert!(read(r#"{"byr":1931}"#.as_bytes(), Format::JsonLines).is_err());
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let args = ["--to", "csv", "--drop-invalid"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        let options = parse_options(&args).unwrap();
+        assert_eq!(options.to, Format::Csv);
+        assert_eq!(options.from, None);
+        assert!(options.drop_invalid);
+        assert!(parse_options(&["--to".to_string()]).is_err());
+        assert!(parse_options(&["--to".to_string(), "xml".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,243 @@
+use crate::schema::{Schema, Violation};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead};
+
+#[derive(Hash, Eq, PartialEq, Debug, Clone, Default)]
+pub struct Passport {
+    pub fields: Vec<(String, String)>,
+}
+
+impl Passport {
+    // the first value wins if a key was given more than once
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn unknown_fields(&self, schema: &Schema) -> Vec<&str> {
+        self.fields
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .filter(|key| !schema.fields.iter().any(|field| field.name == *key))
+            .collect()
+    }
+
+    pub fn violations(&self, schema: &Schema) -> Vec<Violation> {
+        schema.validate(self)
+    }
+
+    pub fn is_valid_part1(&self, schema: &Schema) -> bool {
+        self.violations(schema)
+            .iter()
+            .all(|v| !matches!(v, Violation::Missing { .. }))
+    }
+
+    pub fn is_valid_part2(&self, schema: &Schema) -> bool {
+        self.violations(schema).is_empty()
+    }
+
+    // the same fields in any order make the same passport
+    fn canonical(&self) -> Vec<(String, String)> {
+        let mut fields = self.fields.clone();
+        fields.sort();
+        fields
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ParseWarning {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Record {
+    pub start: usize,
+    pub end: usize,
+    pub passport: Passport,
+    pub warnings: Vec<ParseWarning>,
+}
+
+impl Record {
+    pub fn new(start: usize) -> Record {
+        Record {
+            start,
+            end: start,
+            passport: Passport::default(),
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn push_line(&mut self, line_number: usize, line: &str) {
+        self.end = line_number;
+        for part in line.split_whitespace() {
+            let kv = part.splitn(2, ':').collect::<Vec<&str>>();
+            let (key, value) = match kv[..] {
+                [key, value] if !key.is_empty() => (key, value),
+                _ => {
+                    self.warnings.push(ParseWarning {
+                        line: line_number,
+                        message: format!("'{}' is not a key:value pair", part),
+                    });
+                    continue;
+                }
+            };
+            if self.passport.get(key).is_some() {
+                self.warnings.push(ParseWarning {
+                    line: line_number,
+                    message: format!("duplicate field '{}', keeping the first value", key),
+                });
+            }
+            self.passport
+                .fields
+                .push((key.to_string(), value.to_string()));
+        }
+    }
+}
+
+// reads the blank-line separated batch format, keeping every record in file order.
+// line numbers are 1-based and the span covers the first to the last line of a record
+pub fn parse_records<R: BufRead>(reader: R) -> io::Result<Vec<Record>> {
+    let mut records = Vec::new();
+    let mut current: Option<Record> = None;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_number = i + 1;
+        if line.trim().is_empty() {
+            if let Some(record) = current.take() {
+                records.push(record);
+            }
+            continue;
+        }
+        current
+            .get_or_insert_with(|| Record::new(line_number))
+            .push_line(line_number, &line);
+    }
+    if let Some(record) = current {
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+pub fn parse_passport(input: &str) -> Passport {
+    let mut record = Record::new(1);
+    record.push_line(1, input);
+    record.passport
+}
+
+// groups of record indices holding the same passport, ordered by first appearance
+pub fn duplicates(records: &[Record]) -> Vec<Vec<usize>> {
+    let mut seen: HashMap<Vec<(String, String)>, usize> = HashMap::new();
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (i, record) in records.iter().enumerate() {
+        let group = *seen.entry(record.passport.canonical()).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(i);
+    }
+    groups.into_iter().filter(|group| group.len() > 1).collect()
+}
+
+pub fn render_duplicates(records: &[Record]) -> String {
+    let groups = duplicates(records);
+    let mut out = String::new();
+    for group in &groups {
+        let spans = group
+            .iter()
+            .map(|i| format!("{}-{}", records[*i].start, records[*i].end))
+            .collect::<Vec<String>>();
+        out.push_str(&format!(
+            "duplicate passport at lines {}\n",
+            spans.join(", ")
+        ));
+    }
+    out.push_str(&format!("{} duplicated passports\n", groups.len()));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BATCH: &str = "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd
+byr:1937 iyr:2017 cid:147 hgt:183cm
+
+iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884
+hcl:#cfa07d byr:1929 foo:bar broken
+
+
+byr:1937 iyr:2017 cid:147 hgt:183cm
+ecl:gry pid:860033327 eyr:2020 hcl:#fffffd
+
+hgt:59in hgt:60in";
+
+    #[test]
+    fn test_parse_records() {
+        let records = parse_records(BATCH.as_bytes()).unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!((records[0].start, records[0].end), (1, 2));
+        assert_eq!((records[1].start, records[1].end), (4, 5));
+        assert_eq!((records[2].start, records[2].end), (8, 9));
+        assert_eq!((records[3].start, records[3].end), (11, 11));
+
+        assert_eq!(records[0].passport.fields[0], ("ecl".into(), "gry".into()));
+        assert_eq!(records[1].passport.get("foo"), Some("bar"));
+        assert_eq!(
+            records[1].warnings,
+            vec![ParseWarning {
+                line: 5,
+                message: "'broken' is not a key:value pair".to_string(),
+            }]
+        );
+        assert_eq!(
+            records[1].passport.unknown_fields(&Schema::default()),
+            vec!["foo"]
+        );
+        assert_eq!(records[3].passport.get("hgt"), Some("59in"));
+        assert_eq!(records[3].warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let records = parse_records(BATCH.as_bytes()).unwrap();
+        assert_eq!(duplicates(&records), vec![vec![0, 2]]);
+        assert_eq!(
+            render_duplicates(&records),
+            "duplicate passport at lines 1-2, 8-9\n1 duplicated passports\n"
+        );
+    }
+
+    #[test]
+    fn test_passport() {
+        let schema = Schema::default();
+        let passport = parse_passport(
+            "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd byr:1937 iyr:2017 cid:147 hgt:183cm",
+        );
+        assert!(passport.is_valid_part1(&schema));
+        assert!(passport.is_valid_part2(&schema));
+
+        let passport =
+            parse_passport("hcl:#cfa07d eyr:2025 pid:166559648 iyr:2011 ecl:brn hgt:59in");
+        assert!(!passport.is_valid_part1(&schema));
+        assert!(!passport.is_valid_part2(&schema));
+
+        let passport = parse_passport(
+            "eyr:1972 cid:100 hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926",
+        );
+        assert!(passport.is_valid_part1(&schema));
+        assert!(!passport.is_valid_part2(&schema));
+    }
+}
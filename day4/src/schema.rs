@@ -0,0 +1,361 @@
+use crate::passport::Passport;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const DEFAULT_SCHEMA: &str = include_str!("../schema.toml");
+
+#[derive(Debug)]
+pub enum SchemaError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    Regex(regex::Error),
+    UnknownFormat(String),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Io(e) => write!(f, "could not read schema: {}", e),
+            SchemaError::Toml(e) => write!(f, "invalid toml schema: {}", e),
+            SchemaError::Json(e) => write!(f, "invalid json schema: {}", e),
+            SchemaError::Regex(e) => write!(f, "invalid regex in schema: {}", e),
+            SchemaError::UnknownFormat(path) => {
+                write!(
+                    f,
+                    "unknown schema format '{}', expected .toml or .json",
+                    path
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+#[derive(Deserialize)]
+struct SchemaSpec {
+    field: Vec<FieldSpec>,
+}
+
+#[derive(Deserialize)]
+struct FieldSpec {
+    name: String,
+    #[serde(default)]
+    required: bool,
+    rule: Option<RuleSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum RuleSpec {
+    IntRange {
+        min: i64,
+        max: i64,
+        digits: Option<usize>,
+    },
+    UnitRange {
+        units: Vec<UnitSpec>,
+    },
+    Regex {
+        pattern: String,
+    },
+    Enum {
+        values: Vec<String>,
+    },
+    Digits {
+        length: usize,
+    },
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct UnitSpec {
+    pub unit: String,
+    pub min: i64,
+    pub max: i64,
+}
+
+#[derive(Debug)]
+pub enum Rule {
+    IntRange {
+        min: i64,
+        max: i64,
+        digits: Option<usize>,
+    },
+    UnitRange(Vec<UnitSpec>),
+    Regex(Regex),
+    Enum(Vec<String>),
+    Digits(usize),
+}
+
+impl TryFrom<RuleSpec> for Rule {
+    type Error = SchemaError;
+
+    fn try_from(spec: RuleSpec) -> Result<Self, Self::Error> {
+        Ok(match spec {
+            RuleSpec::IntRange { min, max, digits } => Rule::IntRange { min, max, digits },
+            RuleSpec::UnitRange { units } => Rule::UnitRange(units),
+            RuleSpec::Regex { pattern } => {
+                Rule::Regex(Regex::new(&pattern).map_err(SchemaError::Regex)?)
+            }
+            RuleSpec::Enum { values } => Rule::Enum(values),
+            RuleSpec::Digits { length } => Rule::Digits(length),
+        })
+    }
+}
+
+fn all_digits(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())
+}
+
+impl Rule {
+    // returns a description of the violated constraint if value does not pass
+    pub fn check(&self, value: &str) -> Result<(), String> {
+        match self {
+            Rule::IntRange { min, max, digits } => {
+                if let Some(n) = digits {
+                    if value.len() != *n || !all_digits(value) {
+                        return Err(format!("must be {} digits", n));
+                    }
+                }
+                match value.parse::<i64>() {
+                    Ok(v) if (*min..=*max).contains(&v) => Ok(()),
+                    _ => Err(format!("must be {}..={}", min, max)),
+                }
+            }
+            Rule::UnitRange(units) => {
+                let split = value
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(value.len());
+                let (number, unit) = value.split_at(split);
+                let spec = match units.iter().find(|u| u.unit == unit) {
+                    Some(spec) => spec,
+                    None => {
+                        let names = units.iter().map(|u| u.unit.as_str()).collect::<Vec<_>>();
+                        return Err(format!("unit must be one of {}", names.join(", ")));
+                    }
+                };
+                match number.parse::<i64>() {
+                    Ok(v) if (spec.min..=spec.max).contains(&v) => Ok(()),
+                    _ => Err(format!("{} must be {}..={}", unit, spec.min, spec.max)),
+                }
+            }
+            Rule::Regex(re) => match re.is_match(value) {
+                true => Ok(()),
+                false => Err(format!("must match {}", re.as_str())),
+            },
+            Rule::Enum(values) => match values.iter().any(|v| v == value) {
+                true => Ok(()),
+                false => Err(format!("must be one of {}", values.join(", "))),
+            },
+            Rule::Digits(length) => match value.len() == *length && all_digits(value) {
+                true => Ok(()),
+                false => Err(format!("must be {} digits", length)),
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Violation {
+    Missing {
+        field: String,
+    },
+    Invalid {
+        field: String,
+        value: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Missing { field } => write!(f, "missing {}", field),
+            Violation::Invalid {
+                field,
+                value,
+                reason,
+            } => write!(f, "{}={}: {}", field, value, reason),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Field {
+    pub name: String,
+    pub required: bool,
+    pub rule: Option<Rule>,
+}
+
+#[derive(Debug)]
+pub struct Schema {
+    pub fields: Vec<Field>,
+}
+
+impl Schema {
+    fn from_spec(spec: SchemaSpec) -> Result<Schema, SchemaError> {
+        let mut fields = Vec::new();
+        for field in spec.field {
+            fields.push(Field {
+                name: field.name,
+                required: field.required,
+                rule: field.rule.map(Rule::try_from).transpose()?,
+            });
+        }
+        Ok(Schema { fields })
+    }
+
+    pub fn from_toml(input: &str) -> Result<Schema, SchemaError> {
+        Schema::from_spec(toml::from_str(input).map_err(SchemaError::Toml)?)
+    }
+
+    pub fn from_json(input: &str) -> Result<Schema, SchemaError> {
+        Schema::from_spec(serde_json::from_str(input).map_err(SchemaError::Json)?)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Schema, SchemaError> {
+        let path = path.as_ref();
+        let input = fs::read_to_string(path).map_err(SchemaError::Io)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Schema::from_toml(&input),
+            Some("json") => Schema::from_json(&input),
+            _ => Err(SchemaError::UnknownFormat(path.display().to_string())),
+        }
+    }
+}
+
+impl Schema {
+    // checks every declared field, in schema order; fields the schema does not know are ignored
+    pub fn validate(&self, passport: &Passport) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for field in &self.fields {
+            match (passport.get(&field.name), &field.rule) {
+                (None, _) if field.required => violations.push(Violation::Missing {
+                    field: field.name.clone(),
+                }),
+                (Some(value), Some(rule)) => {
+                    if let Err(reason) = rule.check(value) {
+                        violations.push(Violation::Invalid {
+                            field: field.name.clone(),
+                            value: value.to_string(),
+                            reason,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        violations
+    }
+}
+
+impl Default for Schema {
+    fn default() -> Self {
+        Schema::from_toml(DEFAULT_SCHEMA).expect("built-in schema is invalid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::passport::parse_passport;
+
+    fn rule<'a>(schema: &'a Schema, name: &str) -> &'a Rule {
+        let field = schema.fields.iter().find(|f| f.name == name).unwrap();
+        field.rule.as_ref().unwrap()
+    }
+
+    fn check(name: &str, value: &str) -> bool {
+        rule(&Schema::default(), name).check(value).is_ok()
+    }
+
+    #[test]
+    fn test_int_range() {
+        let rule = Rule::IntRange {
+            min: 2010,
+            max: 2020,
+            digits: Some(4),
+        };
+        assert!(rule.check("2020").is_ok());
+        assert!(rule.check("2021").is_err());
+        assert!(rule.check("2000").is_err());
+        assert!(rule.check("").is_err());
+        assert!(rule.check("22222").is_err());
+        assert!(check("byr", "2002"));
+        assert!(!check("byr", "2003"));
+    }
+
+    #[test]
+    fn test_unit_range() {
+        assert!(!check("hgt", ""));
+        assert!(check("hgt", "60in"));
+        assert!(check("hgt", "190cm"));
+        assert!(!check("hgt", "190in"));
+        assert!(!check("hgt", "190"));
+        assert!(!check("hgt", "in"));
+
+        let schema = Schema::default();
+        let rule = rule(&schema, "hgt");
+        assert_eq!(rule.check("190in"), Err("in must be 59..=76".to_string()));
+        assert_eq!(
+            rule.check("190"),
+            Err("unit must be one of cm, in".to_string())
+        );
+    }
+
+    #[test]
+    fn test_colors() {
+        assert!(check("hcl", "#123abc"));
+        assert!(!check("hcl", "#123abz"));
+        assert!(!check("hcl", "123abc"));
+
+        assert!(check("ecl", "brn"));
+        assert!(!check("ecl", "wat"));
+    }
+
+    #[test]
+    fn test_digits() {
+        assert!(check("pid", "000000001"));
+        assert!(!check("pid", "01234567890"));
+        assert!(!check("pid", "00000000a"));
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = Schema::default();
+        let violations = schema.validate(&parse_passport("byr:1937 hgt:190in"));
+        assert_eq!(violations.len(), 6);
+        assert_eq!(violations[0].to_string(), "missing iyr");
+        assert_eq!(violations[2].to_string(), "hgt=190in: in must be 59..=76");
+    }
+
+    #[test]
+    fn test_load_json() {
+        let schema = Schema::from_json(
+            r#"{"field": [
+                {"name": "byr", "required": true, "rule": {"type": "int_range", "min": 1, "max": 2}},
+                {"name": "cid"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(schema.fields.len(), 2);
+        assert!(schema.fields[0].required);
+        assert!(!schema.fields[1].required);
+        assert!(schema.fields[1].rule.is_none());
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        let schema = Schema::from_json(
+            r#"{"field": [{"name": "hcl", "rule": {"type": "regex", "pattern": "("}}]}"#,
+        );
+        assert!(matches!(schema, Err(SchemaError::Regex(_))));
+    }
+}
@@ -1,6 +1,9 @@
+use rayon::prelude::*;
 use std::env;
+use std::error::Error;
+use std::fmt;
 use std::fs::File;
-use std::io::{self, BufRead};
+use std::io::{self, BufRead, BufReader};
 use std::path::Path;
 fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
 where
@@ -10,65 +13,457 @@ where
     Ok(io::BufReader::new(file).lines())
 }
 
-const MAX_ROWS: usize = 128;
-const MAX_COLS: usize = 8;
+#[derive(Debug, PartialEq)]
+enum PassError {
+    Length { expected: usize, found: usize },
+    InvalidChar { position: usize, found: char },
+    SeatOutOfRange { row: usize, col: usize },
+}
+
+impl fmt::Display for PassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PassError::Length { expected, found } => {
+                write!(f, "expected {} characters, found {}", expected, found)
+            }
+            PassError::InvalidChar { position, found } => {
+                write!(f, "'{}' at position {} is invalid", found, position)
+            }
+            PassError::SeatOutOfRange { row, col } => {
+                write!(f, "seat {}/{} is not on the plane", row, col)
+            }
+        }
+    }
+}
+
+impl Error for PassError {}
+
+// encodes and decodes boarding passes for a plane with 2^row_bits rows and 2^col_bits columns.
+// every letter halves the remaining range, the first of each alphabet pair keeps the lower half
+#[derive(Debug, Clone)]
+struct BoardingPass {
+    row_bits: usize,
+    col_bits: usize,
+    row_letters: (char, char),
+    col_letters: (char, char),
+}
+
+impl Default for BoardingPass {
+    fn default() -> Self {
+        BoardingPass::new(7, 3, ('F', 'B'), ('L', 'R'))
+    }
+}
+
+impl BoardingPass {
+    pub fn new(
+        row_bits: usize,
+        col_bits: usize,
+        row_letters: (char, char),
+        col_letters: (char, char),
+    ) -> BoardingPass {
+        BoardingPass {
+            row_bits,
+            col_bits,
+            row_letters,
+            col_letters,
+        }
+    }
 
-fn seat_id(row: usize, column: usize) -> usize {
-    return row * 8 + column;
+    pub fn rows(&self) -> usize {
+        1 << self.row_bits
+    }
+
+    pub fn cols(&self) -> usize {
+        1 << self.col_bits
+    }
+
+    pub fn seat_id(&self, row: usize, col: usize) -> usize {
+        row * self.cols() + col
+    }
+
+    pub fn decode(&self, code: &str) -> Result<(usize, usize, usize), PassError> {
+        let chars = code.chars().collect::<Vec<char>>();
+        let expected = self.row_bits + self.col_bits;
+        if chars.len() != expected {
+            return Err(PassError::Length {
+                expected,
+                found: chars.len(),
+            });
+        }
+        let (row_part, col_part) = chars.split_at(self.row_bits);
+        let row = binary_search(row_part, self.rows(), self.row_letters, 0)?;
+        let col = binary_search(col_part, self.cols(), self.col_letters, self.row_bits)?;
+        Ok((row, col, self.seat_id(row, col)))
+    }
+
+    pub fn encode(&self, row: usize, col: usize) -> Result<String, PassError> {
+        if row >= self.rows() || col >= self.cols() {
+            return Err(PassError::SeatOutOfRange { row, col });
+        }
+        let mut code = String::new();
+        for bit in (0..self.row_bits).rev() {
+            code.push(match row >> bit & 1 {
+                0 => self.row_letters.0,
+                _ => self.row_letters.1,
+            });
+        }
+        for bit in (0..self.col_bits).rev() {
+            code.push(match col >> bit & 1 {
+                0 => self.col_letters.0,
+                _ => self.col_letters.1,
+            });
+        }
+        Ok(code)
+    }
+
+    // reads the whole code as one binary number, the high letters being 1 bits.
+    // because there are 2^col_bits columns this is the seat id
+    pub fn decode_bits(&self, code: &str) -> Result<usize, PassError> {
+        if !code.is_ascii() {
+            return self.decode(code).map(|(_, _, id)| id);
+        }
+        let bytes = code.as_bytes();
+        let expected = self.row_bits + self.col_bits;
+        if bytes.len() != expected {
+            return Err(PassError::Length {
+                expected,
+                found: bytes.len(),
+            });
+        }
+        let mut id = 0;
+        for (i, b) in bytes.iter().enumerate() {
+            let letters = match i < self.row_bits {
+                true => self.row_letters,
+                false => self.col_letters,
+            };
+            let bit = match *b as char {
+                c if c == letters.1 => 1,
+                c if c == letters.0 => 0,
+                _ => {
+                    return Err(PassError::InvalidChar {
+                        position: i,
+                        found: *b as char,
+                    })
+                }
+            };
+            id = id << 1 | bit;
+        }
+        Ok(id)
+    }
 }
 
-fn binary_search(line: &str, max: usize) -> usize {
+// offset is the position of part inside the whole code, for error reporting
+fn binary_search(
+    part: &[char],
+    max: usize,
+    letters: (char, char),
+    offset: usize,
+) -> Result<usize, PassError> {
     let mut left = 0;
     let mut right = max - 1;
-    for c in line.chars() {
+    for (i, c) in part.iter().enumerate() {
         let center = (left + right) / 2;
-        match c {
-            'F' | 'L' => {
-                right = center;
+        match *c {
+            c if c == letters.0 => right = center,
+            c if c == letters.1 => left = center + 1,
+            found => {
+                return Err(PassError::InvalidChar {
+                    position: offset + i,
+                    found,
+                })
             }
-            'B' | 'R' => {
-                left = center + 1;
-            }
-            _ => eprintln!("error row part \"{}\", '{}' is invalid", line, c),
         };
     }
-    return left;
+    Ok(left)
 }
 
-fn parse_pass(line: &str) -> usize {
-    let row_part = &line[0..7];
-    let col_part = &line[7..];
-    let row = binary_search(row_part, MAX_ROWS);
-    let col = binary_search(col_part, MAX_COLS);
-    return seat_id(row, col);
+// one bit per seat id, for manifests too large to keep every pass around
+#[derive(Clone)]
+struct SeatBits {
+    len: usize,
+    words: Vec<u64>,
 }
 
-fn main() -> Result<(), io::Error> {
-    let args: Vec<String> = env::args().collect();
-    let mut max_id = 0;
-    let mut boarding_passes: Vec<usize> = Vec::new();
-    if let Ok(lines) = read_lines(&args[1]) {
-        for (i, line) in lines.enumerate() {
-            match line {
-                Ok(row) => {
-                    let id = parse_pass(&row);
-                    boarding_passes.push(id);
-                    if id > max_id {
-                        max_id = id;
-                    }
-                }
-                Err(e) => {
-                    eprintln!("error in line {}: {}", i, e);
-                    return Err(e);
+impl SeatBits {
+    pub fn new(len: usize) -> SeatBits {
+        SeatBits {
+            len,
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    pub fn insert(&mut self, id: usize) {
+        self.words[id / 64] |= 1 << (id % 64);
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.words[id / 64] & 1 << (id % 64) != 0
+    }
+
+    pub fn union_with(&mut self, other: &SeatBits) {
+        for (word, other) in self.words.iter_mut().zip(&other.words) {
+            *word |= other;
+        }
+    }
+
+    pub fn max_id(&self) -> Option<usize> {
+        self.words
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, word)| **word != 0)
+            .map(|(i, word)| i * 64 + 63 - word.leading_zeros() as usize)
+    }
+
+    pub fn free_seats(&self) -> Vec<usize> {
+        (1..self.len.saturating_sub(1))
+            .filter(|id| !self.contains(*id) && self.contains(id - 1) && self.contains(id + 1))
+            .collect()
+    }
+}
+
+const CHUNK_LINES: usize = 1 << 16;
+
+// decodes the manifest chunk by chunk in parallel, so memory stays bounded by the chunk size
+fn scan_bits<R: BufRead>(reader: R, codec: &BoardingPass) -> Result<SeatBits, Box<dyn Error>> {
+    let n_seats = codec.rows() * codec.cols();
+    let mut seats = SeatBits::new(n_seats);
+    let mut lines = reader.lines();
+    let mut chunk: Vec<String> = Vec::with_capacity(CHUNK_LINES);
+    let mut line_number = 0;
+    loop {
+        chunk.clear();
+        for line in lines.by_ref().take(CHUNK_LINES) {
+            chunk.push(line?);
+        }
+        if chunk.is_empty() {
+            break;
+        }
+        let bits = chunk
+            .par_iter()
+            .enumerate()
+            .try_fold(
+                || SeatBits::new(n_seats),
+                |mut bits, (i, code)| {
+                    let id = codec.decode_bits(code).map_err(|e| {
+                        format!("error in line {}: \"{}\": {}", line_number + i + 1, code, e)
+                    })?;
+                    bits.insert(id);
+                    Ok::<SeatBits, String>(bits)
+                },
+            )
+            .try_reduce(
+                || SeatBits::new(n_seats),
+                |mut a, b| {
+                    a.union_with(&b);
+                    Ok(a)
+                },
+            )?;
+        seats.union_with(&bits);
+        line_number += chunk.len();
+    }
+    Ok(seats)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Gap {
+    // before the first boarded seat
+    Front,
+    // after the last boarded seat
+    Back,
+    Interior,
+}
+
+#[derive(Debug, PartialEq)]
+struct Seat {
+    row: usize,
+    col: usize,
+    id: usize,
+}
+
+// how many passes were seen for every seat on the plane, indexed by seat id
+struct SeatMap {
+    rows: usize,
+    cols: usize,
+    boarded: Vec<usize>,
+}
+
+impl SeatMap {
+    pub fn new(rows: usize, cols: usize) -> SeatMap {
+        SeatMap {
+            rows,
+            cols,
+            boarded: vec![0; rows * cols],
+        }
+    }
+
+    pub fn board(&mut self, id: usize) {
+        self.boarded[id] += 1;
+    }
+
+    fn seat(&self, id: usize) -> Seat {
+        Seat {
+            row: id / self.cols,
+            col: id % self.cols,
+            id,
+        }
+    }
+
+    pub fn max_id(&self) -> Option<usize> {
+        self.boarded.iter().rposition(|n| *n > 0)
+    }
+
+    pub fn missing(&self) -> Vec<(Seat, Gap)> {
+        let first = self.boarded.iter().position(|n| *n > 0);
+        let last = self.max_id();
+        self.boarded
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| **n == 0)
+            .map(|(id, _)| {
+                let gap = match (first, last) {
+                    (Some(first), _) if id < first => Gap::Front,
+                    (_, Some(last)) if id > last => Gap::Back,
+                    (Some(_), Some(_)) => Gap::Interior,
+                    // nobody boarded at all
+                    _ => Gap::Front,
+                };
+                (self.seat(id), gap)
+            })
+            .collect()
+    }
+
+    // the puzzle's own seat: missing, but the ids on both sides are taken
+    pub fn free_seats(&self) -> Vec<Seat> {
+        (1..self.boarded.len().saturating_sub(1))
+            .filter(|id| {
+                self.boarded[*id] == 0 && self.boarded[id - 1] > 0 && self.boarded[id + 1] > 0
+            })
+            .map(|id| self.seat(id))
+            .collect()
+    }
+
+    pub fn duplicates(&self) -> Vec<(Seat, usize)> {
+        self.boarded
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| **n > 1)
+            .map(|(id, n)| (self.seat(id), *n))
+            .collect()
+    }
+
+    pub fn report(&self) -> String {
+        let missing = self.missing();
+        let mut out = String::new();
+        for gap in &[Gap::Front, Gap::Back] {
+            let rows = missing
+                .iter()
+                .filter(|(_, g)| g == gap)
+                .map(|(seat, _)| seat.row)
+                .collect::<Vec<usize>>();
+            if let (Some(first), Some(last)) = (rows.first(), rows.last()) {
+                out.push_str(&format!(
+                    "{:?} missing: {} seats, rows {}..={}\n",
+                    gap,
+                    rows.len(),
+                    first,
+                    last
+                ));
+            }
+        }
+        for (seat, gap) in &missing {
+            if *gap == Gap::Interior {
+                out.push_str(&format!(
+                    "Interior missing: row {} col {} id {}\n",
+                    seat.row, seat.col, seat.id
+                ));
+            }
+        }
+        for (seat, n) in self.duplicates() {
+            out.push_str(&format!(
+                "Duplicate: row {} col {} id {} boarded {} times\n",
+                seat.row, seat.col, seat.id, n
+            ));
+        }
+        out
+    }
+}
+
+// one line per row, '#' for a boarded seat, '.' for an empty one and '!' for a duplicate
+impl fmt::Display for SeatMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = (self.rows - 1).to_string().len();
+        for row in 0..self.rows {
+            write!(f, "{:>width$} ", row, width = width)?;
+            for n in &self.boarded[row * self.cols..(row + 1) * self.cols] {
+                match n {
+                    0 => write!(f, ".")?,
+                    1 => write!(f, "#")?,
+                    _ => write!(f, "!")?,
                 }
             }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+    let mode = args.get(2).map_or("", String::as_str);
+    let codec = BoardingPass::default();
+
+    // input lines are "row col" pairs, printed back as their codes
+    if mode == "encode" {
+        for (i, line) in read_lines(&args[1])?.enumerate() {
+            let row = line?;
+            let seat = row
+                .split_whitespace()
+                .map(|n| n.parse::<usize>())
+                .collect::<Result<Vec<usize>, _>>()
+                .map_err(|e| format!("error in line {}: \"{}\": {}", i + 1, row, e))?;
+            if let [seat_row, seat_col] = seat[..] {
+                println!("{}", codec.encode(seat_row, seat_col)?);
+            } else {
+                return Err(format!("error in line {}: expected \"row col\"", i + 1).into());
+            }
         }
+        return Ok(());
     }
-    boarding_passes.sort();
-    println!("{}", max_id);
-    for id in 1..boarding_passes.len() - 2 {
-        if boarding_passes[id - 1] + 1 != boarding_passes[id] {
-            println!("{}", boarding_passes[id] - 1);
+
+    if mode == "fast" {
+        let seats = scan_bits(BufReader::new(File::open(&args[1])?), &codec)?;
+        if let Some(max_id) = seats.max_id() {
+            println!("{}", max_id);
+        }
+        for id in seats.free_seats() {
+            println!("{}", id);
+        }
+        return Ok(());
+    }
+
+    let mut seats = SeatMap::new(codec.rows(), codec.cols());
+    for (i, line) in read_lines(&args[1])?.enumerate() {
+        let row = line?;
+        let (seat_row, seat_col, id) = codec
+            .decode(&row)
+            .map_err(|e| format!("error in line {}: \"{}\": {}", i + 1, row, e))?;
+        if mode == "decode" {
+            println!("{} {} {}", seat_row, seat_col, id);
+        }
+        seats.board(id);
+    }
+
+    if mode == "map" {
+        print!("{}", seats);
+    } else if mode == "seats" {
+        print!("{}", seats.report());
+    } else if mode != "decode" {
+        if let Some(max_id) = seats.max_id() {
+            println!("{}", max_id);
+        }
+        for seat in seats.free_seats() {
+            println!("{}", seat.id);
         }
     }
     Ok(())
@@ -80,9 +475,188 @@ mod tests {
 
     #[test]
     fn test_parse_pass() {
-        assert_eq!(parse_pass("FBFBBFFRLR"), 357);
-        assert_eq!(parse_pass("BFFFBBFRRR"), 567);
-        assert_eq!(parse_pass("FFFBBBFRRR"), 119);
-        assert_eq!(parse_pass("BBFFBBFRLL"), 820);
+        let codec = BoardingPass::default();
+        assert_eq!(codec.decode("FBFBBFFRLR"), Ok((44, 5, 357)));
+        assert_eq!(codec.decode("BFFFBBFRRR"), Ok((70, 7, 567)));
+        assert_eq!(codec.decode("FFFBBBFRRR"), Ok((14, 7, 119)));
+        assert_eq!(codec.decode("BBFFBBFRLL"), Ok((102, 4, 820)));
+    }
+
+    #[test]
+    fn test_encode() {
+        let codec = BoardingPass::default();
+        assert_eq!(codec.encode(44, 5), Ok("FBFBBFFRLR".to_string()));
+        assert_eq!(codec.encode(102, 4), Ok("BBFFBBFRLL".to_string()));
+        assert_eq!(
+            codec.encode(128, 0),
+            Err(PassError::SeatOutOfRange { row: 128, col: 0 })
+        );
+        for id in 0..codec.seat_id(127, 7) {
+            let code = codec.encode(id / 8, id % 8).unwrap();
+            assert_eq!(codec.decode(&code).unwrap().2, id);
+        }
+    }
+
+    #[test]
+    fn test_geometry() {
+        let codec = BoardingPass::new(2, 1, ('0', '1'), ('a', 'b'));
+        assert_eq!(codec.decode("10b"), Ok((2, 1, 5)));
+        assert_eq!(codec.encode(3, 0), Ok("11a".to_string()));
+    }
+
+    #[test]
+    fn test_invalid_pass() {
+        let codec = BoardingPass::default();
+        assert_eq!(
+            codec.decode("FBFBBFFRLX"),
+            Err(PassError::InvalidChar {
+                position: 9,
+                found: 'X'
+            })
+        );
+        assert_eq!(
+            codec.decode("FBFRBFFRLR"),
+            Err(PassError::InvalidChar {
+                position: 3,
+                found: 'R'
+            })
+        );
+        assert_eq!(
+            codec.decode("FBF"),
+            Err(PassError::Length {
+                expected: 10,
+                found: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_seat_map() {
+        let mut seats = SeatMap::new(4, 2);
+        for id in &[2, 3, 5, 6, 6] {
+            seats.board(*id);
+        }
+        assert_eq!(seats.max_id(), Some(6));
+        assert_eq!(
+            seats.missing(),
+            vec![
+                (
+                    Seat {
+                        row: 0,
+                        col: 0,
+                        id: 0
+                    },
+                    Gap::Front
+                ),
+                (
+                    Seat {
+                        row: 0,
+                        col: 1,
+                        id: 1
+                    },
+                    Gap::Front
+                ),
+                (
+                    Seat {
+                        row: 2,
+                        col: 0,
+                        id: 4
+                    },
+                    Gap::Interior
+                ),
+                (
+                    Seat {
+                        row: 3,
+                        col: 1,
+                        id: 7
+                    },
+                    Gap::Back
+                ),
+            ]
+        );
+        assert_eq!(
+            seats.free_seats(),
+            vec![Seat {
+                row: 2,
+                col: 0,
+                id: 4
+            }]
+        );
+        assert_eq!(
+            seats.duplicates(),
+            vec![(
+                Seat {
+                    row: 3,
+                    col: 0,
+                    id: 6
+                },
+                2
+            )]
+        );
+        assert_eq!(seats.to_string(), "0 ..\n1 ##\n2 .#\n3 !.\n");
+        assert_eq!(
+            seats.report(),
+            "Front missing: 2 seats, rows 0..=0
+Back missing: 1 seats, rows 3..=3
+Interior missing: row 2 col 0 id 4
+Duplicate: row 3 col 0 id 6 boarded 2 times
+"
+        );
+    }
+
+    #[test]
+    fn test_empty_seat_map() {
+        let seats = SeatMap::new(2, 2);
+        assert_eq!(seats.max_id(), None);
+        assert!(seats.free_seats().is_empty());
+        assert_eq!(seats.missing().len(), 4);
+        assert!(SeatMap::new(1, 1).free_seats().is_empty());
+    }
+
+    #[test]
+    fn test_decode_bits() {
+        let codec = BoardingPass::default();
+        for id in 0..1024 {
+            let code = codec.encode(id / 8, id % 8).unwrap();
+            assert_eq!(codec.decode_bits(&code), Ok(id));
+        }
+        assert_eq!(
+            codec.decode_bits("FBFBBFFRLX"),
+            Err(PassError::InvalidChar {
+                position: 9,
+                found: 'X'
+            })
+        );
+        assert!(codec.decode_bits("FBFBBFFRLé").is_err());
+        assert_eq!(
+            codec.decode_bits("FBFBBFFRL"),
+            Err(PassError::Length {
+                expected: 10,
+                found: 9
+            })
+        );
+    }
+
+    #[test]
+    fn test_scan_bits() {
+        let codec = BoardingPass::default();
+        let manifest = (100..200)
+            .chain(201..300)
+            .chain(150..160)
+            .map(|id| codec.encode(id / 8, id % 8).unwrap())
+            .collect::<Vec<String>>()
+            .join("\n");
+        let seats = scan_bits(manifest.as_bytes(), &codec).unwrap();
+        assert_eq!(seats.max_id(), Some(299));
+        assert_eq!(seats.free_seats(), vec![200]);
+        assert!(seats.contains(100));
+        assert!(!seats.contains(99));
+
+        let error = scan_bits("FBFBBFFRLR\nFBFBBFFRLR\nFBFBBFFXLR".as_bytes(), &codec);
+        assert_eq!(
+            error.err().unwrap().to_string(),
+            "error in line 3: \"FBFBBFFXLR\": 'X' at position 7 is invalid"
+        );
+        assert_eq!(scan_bits("".as_bytes(), &codec).unwrap().max_id(), None);
     }
 }
@@ -1,91 +1,227 @@
+mod stats;
+
+use stats::Stats;
 use std::env;
+use std::error::Error;
+use std::fmt;
 use std::fs::File;
 use std::io::{self, BufRead, BufReader};
 
-fn unique_answers(line: &str) -> usize {
-    let mut uniques: Vec<char> = Vec::new();
-    for c in line.chars() {
-        if !uniques.contains(&c) {
-            uniques.push(c);
+#[derive(Debug, PartialEq)]
+struct InvalidChar {
+    line: usize,
+    column: usize,
+    found: char,
+}
+
+#[derive(Debug)]
+enum ParseError {
+    Io(io::Error),
+    InvalidUtf8 { line: usize },
+    InvalidChars(Vec<InvalidChar>),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Io(e) => write!(f, "{}", e),
+            ParseError::InvalidUtf8 { line } => write!(f, "line {}: invalid utf-8", line),
+            ParseError::InvalidChars(chars) => {
+                for (i, c) in chars.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(
+                        f,
+                        "line {}, column {}: invalid answer {:?}, expected a-z",
+                        c.line, c.column, c.found
+                    )?;
+                }
+                Ok(())
+            }
         }
     }
-    return uniques.len();
 }
 
-fn part1(file: &str) -> usize {
-    let mut f = BufReader::new(File::open(file).expect("File::open failed"));
-    let mut buf: Vec<u8> = Vec::new();
-    let mut group: String = "".to_owned();
-    let mut sum = 0;
-    while f.read_until(b'\n', &mut buf).expect("f.read_until failed") != 0 {
-        let s = String::from_utf8(buf).expect("String::from_utf8 falied");
-        let trimmed = s.trim();
-        if trimmed.len() == 0 {
-            sum += unique_answers(&group);
-            group.clear();
-        } else {
-            group.push_str(trimmed);
+impl Error for ParseError {}
+
+impl From<io::Error> for ParseError {
+    fn from(e: io::Error) -> Self {
+        ParseError::Io(e)
+    }
+}
+
+// the questions a-z, one bit each
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct Answers(u32);
+
+impl Answers {
+    const ALL: Answers = Answers((1 << 26) - 1);
+
+    // on failure returns every character outside a-z with its 1-based column
+    pub fn parse(line: &str) -> Result<Answers, Vec<(usize, char)>> {
+        let mut bits = 0;
+        let mut invalid = Vec::new();
+        for (i, c) in line.chars().enumerate() {
+            match c {
+                'a'..='z' => bits |= 1 << (c as u32 - 'a' as u32),
+                _ => invalid.push((i + 1, c)),
+            }
         }
-        buf = s.into_bytes();
-        buf.clear();
+        match invalid.is_empty() {
+            true => Ok(Answers(bits)),
+            false => Err(invalid),
+        }
+    }
+
+    pub fn count(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn contains(self, question: usize) -> bool {
+        self.0 & 1 << question != 0
+    }
+
+    pub fn union(self, other: Answers) -> Answers {
+        Answers(self.0 | other.0)
     }
 
-    if group.len() > 0 {
-        sum += unique_answers(&group);
-        group.clear();
+    pub fn intersection(self, other: Answers) -> Answers {
+        Answers(self.0 & other.0)
     }
-    return sum;
 }
 
-fn every_answer(group: &str) -> usize {
-    let mut answers: Vec<char> = Vec::new();
-    let mut members = group.split_whitespace().collect::<Vec<&str>>();
-    members.sort_by(|a, b| b.len().cmp(&a.len()));
-    for chr in members[0].chars() {
-        let mut all_have = true;
-        for i in 1..members.len() {
-            if !members[i].contains(chr) {
-                all_have = false;
+#[derive(Debug, Default, PartialEq)]
+struct Group {
+    members: Vec<Answers>,
+}
+
+impl Group {
+    // questions anyone in the group answered yes to
+    pub fn anyone(&self) -> Answers {
+        self.members
+            .iter()
+            .fold(Answers::default(), |acc, m| acc.union(*m))
+    }
+
+    // questions everyone in the group answered yes to
+    pub fn everyone(&self) -> Answers {
+        match self.members.is_empty() {
+            true => Answers::default(),
+            false => self
+                .members
+                .iter()
+                .fold(Answers::ALL, |acc, m| acc.intersection(*m)),
+        }
+    }
+
+    // questions exactly k members answered yes to
+    pub fn exactly(&self, k: usize) -> Answers {
+        let histogram = self.histogram();
+        let mut bits = 0;
+        for (question, n) in histogram.iter().enumerate() {
+            if *n == k {
+                bits |= 1 << question;
             }
         }
-        if all_have {
-            answers.push(chr);
+        Answers(bits)
+    }
+
+    // how many members answered yes to each question
+    pub fn histogram(&self) -> [usize; 26] {
+        let mut histogram = [0; 26];
+        for member in &self.members {
+            for (question, n) in histogram.iter_mut().enumerate() {
+                if member.contains(question) {
+                    *n += 1;
+                }
+            }
         }
+        histogram
     }
-    return answers.len();
 }
 
-fn part2(file: &str) -> usize {
-    let mut sum = 0;
-    let mut group = String::new();
-
-    let mut f = BufReader::new(File::open(file).expect("File::open failed"));
+// groups are separated by lines that are empty or whitespace only, answers may be
+// surrounded by whitespace and lines may end in \n or \r\n
+fn read_groups<R: BufRead>(mut reader: R) -> Result<Vec<Group>, ParseError> {
+    let mut groups = Vec::new();
+    let mut group = Group::default();
+    let mut invalid = Vec::new();
     let mut buf: Vec<u8> = Vec::new();
-    while f.read_until(b'\n', &mut buf).expect("f.read_until failed") != 0 {
-        let s = String::from_utf8(buf).expect("String::from_utf8 falied");
-        if s == "\n" {
-            sum += every_answer(&group);
-            group.clear();
+    let mut line_number = 0;
+    while reader.read_until(b'\n', &mut buf)? != 0 {
+        line_number += 1;
+        let line =
+            std::str::from_utf8(&buf).map_err(|_| ParseError::InvalidUtf8 { line: line_number })?;
+        let leading = line.len() - line.trim_start().len();
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            if !group.members.is_empty() {
+                groups.push(group);
+                group = Group::default();
+            }
         } else {
-            group.push_str(&s);
+            match Answers::parse(trimmed) {
+                Ok(answers) => group.members.push(answers),
+                Err(chars) => {
+                    let offset = line[..leading].chars().count();
+                    for (column, found) in chars {
+                        invalid.push(InvalidChar {
+                            line: line_number,
+                            column: offset + column,
+                            found,
+                        });
+                    }
+                }
+            }
         }
-        buf = s.into_bytes();
         buf.clear();
     }
-    if group.len() > 0 {
-        sum += every_answer(&group);
-        group.clear();
+    if !group.members.is_empty() {
+        groups.push(group);
+    }
+    match invalid.is_empty() {
+        true => Ok(groups),
+        false => Err(ParseError::InvalidChars(invalid)),
     }
-    return sum;
 }
 
-fn main() -> Result<(), io::Error> {
+fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = env::args().collect();
+    let groups = read_groups(BufReader::new(File::open(&args[1])?))?;
 
     if &args[2] == "1" {
-        println!("{}", part1(&args[1]))
+        println!(
+            "{}",
+            groups.iter().map(|g| g.anyone().count()).sum::<usize>()
+        );
     } else if &args[2] == "2" {
-        println!("{}", part2(&args[1]))
+        println!(
+            "{}",
+            groups.iter().map(|g| g.everyone().count()).sum::<usize>()
+        );
+    } else if &args[2] == "exactly" {
+        let k = args[3].parse::<usize>().expect("k NaN");
+        println!(
+            "{}",
+            groups.iter().map(|g| g.exactly(k).count()).sum::<usize>()
+        );
+    } else if &args[2] == "stats" {
+        let stats = Stats::new(&groups);
+        match args.get(3).map(String::as_str) {
+            Some("json") => println!("{}", serde_json::to_string_pretty(&stats)?),
+            _ => print!("{}", stats),
+        }
+    } else if &args[2] == "histogram" {
+        let mut histogram = [0; 26];
+        for group in &groups {
+            for (total, n) in histogram.iter_mut().zip(group.histogram().iter()) {
+                *total += n;
+            }
+        }
+        for (question, n) in histogram.iter().enumerate() {
+            println!("{} {}", (b'a' + question as u8) as char, n);
+        }
     }
     Ok(())
 }
@@ -93,6 +229,103 @@ fn main() -> Result<(), io::Error> {
 mod tests {
     use super::*;
 
+    const TEST: &str = "abc
+
+a
+b
+c
+
+ab
+ac
+
+a
+a
+a
+a
+
+b";
+
+    #[test]
+    fn test_groups() {
+        let groups = read_groups(TEST.as_bytes()).unwrap();
+        assert_eq!(groups.len(), 5);
+        let anyone = groups
+            .iter()
+            .map(|g| g.anyone().count())
+            .collect::<Vec<_>>();
+        assert_eq!(anyone, vec![3, 3, 3, 1, 1]);
+        let everyone = groups
+            .iter()
+            .map(|g| g.everyone().count())
+            .collect::<Vec<_>>();
+        assert_eq!(everyone, vec![3, 0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_exactly() {
+        let group = Group {
+            members: vec![
+                Answers::parse("ab").unwrap(),
+                Answers::parse("ac").unwrap(),
+                Answers::parse("a").unwrap(),
+            ],
+        };
+        assert_eq!(group.exactly(3), Answers::parse("a").unwrap());
+        assert_eq!(group.exactly(1), Answers::parse("bc").unwrap());
+        assert_eq!(group.exactly(2), Answers::default());
+        assert_eq!(group.exactly(0).count(), 23);
+
+        let histogram = group.histogram();
+        assert_eq!(histogram[0], 3);
+        assert_eq!(histogram[1], 1);
+        assert_eq!(histogram[25], 0);
+        assert_eq!(Group::default().everyone(), Answers::default());
+    }
+
+    #[test]
+    fn test_separators() {
+        let input = "abc\r\nab \r\n\r\n  \t\r\n\n\n a\r\nb";
+        let groups = read_groups(input.as_bytes()).unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].members.len(), 2);
+        assert_eq!(groups[0].everyone().count(), 2);
+        assert_eq!(groups[1].anyone().count(), 2);
+        assert!(read_groups("".as_bytes()).unwrap().is_empty());
+        assert!(read_groups("\n\n".as_bytes()).unwrap().is_empty());
+    }
+
     #[test]
-    fn test_stuff() {}
+    fn test_invalid_input() {
+        let error = read_groups("abc\n\n a1\nb C\n".as_bytes()).unwrap_err();
+        match &error {
+            ParseError::InvalidChars(chars) => assert_eq!(
+                chars,
+                &vec![
+                    InvalidChar {
+                        line: 3,
+                        column: 3,
+                        found: '1'
+                    },
+                    InvalidChar {
+                        line: 4,
+                        column: 2,
+                        found: ' '
+                    },
+                    InvalidChar {
+                        line: 4,
+                        column: 3,
+                        found: 'C'
+                    },
+                ]
+            ),
+            _ => panic!("unexpected error {:?}", error),
+        }
+        assert_eq!(
+            error.to_string().lines().next(),
+            Some("line 3, column 3: invalid answer '1', expected a-z")
+        );
+
+        let error = read_groups(&b"abc\n\xff\n"[..]).unwrap_err();
+        assert_eq!(error.to_string(), "line 2: invalid utf-8");
+    }
 }
@@ -0,0 +1,218 @@
+use crate::{Answers, Group};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+const TOP_CORRELATIONS: usize = 10;
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct QuestionStat {
+    pub question: char,
+    pub yes: usize,
+    // of all people
+    pub share: f64,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Correlation {
+    pub a: char,
+    pub b: char,
+    // phi coefficient over all people, -1 to 1
+    pub phi: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub groups: usize,
+    pub people: usize,
+    // sorted by yes count, most common first
+    pub questions: Vec<QuestionStat>,
+    pub most_common: Vec<char>,
+    pub least_common: Vec<char>,
+    // group size -> number of groups
+    pub group_sizes: BTreeMap<usize, usize>,
+    // groups where everyone gave exactly the same answers
+    pub unanimous_groups: usize,
+    // strongest correlations first, questions nobody or everybody answered are skipped
+    pub correlations: Vec<Correlation>,
+}
+
+fn question(index: usize) -> char {
+    (b'a' + index as u8) as char
+}
+
+fn phi(people: &[Answers], a: usize, b: usize) -> Option<f64> {
+    let (mut n11, mut n1x, mut nx1) = (0.0, 0.0, 0.0);
+    for person in people {
+        let (ya, yb) = (person.contains(a), person.contains(b));
+        if ya {
+            n1x += 1.0;
+        }
+        if yb {
+            nx1 += 1.0;
+        }
+        if ya && yb {
+            n11 += 1.0;
+        }
+    }
+    let n = people.len() as f64;
+    let denominator = n1x * (n - n1x) * nx1 * (n - nx1);
+    match denominator > 0.0 {
+        true => Some((n * n11 - n1x * nx1) / denominator.sqrt()),
+        false => None,
+    }
+}
+
+impl Stats {
+    pub fn new(groups: &[Group]) -> Stats {
+        let people = groups
+            .iter()
+            .flat_map(|g| g.members.iter().copied())
+            .collect::<Vec<Answers>>();
+
+        let mut histogram = [0; 26];
+        for group in groups {
+            for (total, n) in histogram.iter_mut().zip(group.histogram().iter()) {
+                *total += n;
+            }
+        }
+        let mut questions = histogram
+            .iter()
+            .enumerate()
+            .map(|(i, yes)| QuestionStat {
+                question: question(i),
+                yes: *yes,
+                share: match people.is_empty() {
+                    true => 0.0,
+                    false => *yes as f64 / people.len() as f64,
+                },
+            })
+            .collect::<Vec<QuestionStat>>();
+        questions.sort_by(|a, b| b.yes.cmp(&a.yes).then(a.question.cmp(&b.question)));
+
+        let max = histogram.iter().max().copied().unwrap_or(0);
+        let min = histogram.iter().min().copied().unwrap_or(0);
+        let with_count = |count: usize| {
+            (0..26)
+                .filter(|i| histogram[*i] == count)
+                .map(question)
+                .collect::<Vec<char>>()
+        };
+
+        let mut group_sizes = BTreeMap::new();
+        for group in groups {
+            *group_sizes.entry(group.members.len()).or_insert(0) += 1;
+        }
+
+        let mut correlations = Vec::new();
+        for a in 0..26 {
+            for b in a + 1..26 {
+                if let Some(phi) = phi(&people, a, b) {
+                    correlations.push(Correlation {
+                        a: question(a),
+                        b: question(b),
+                        phi,
+                    });
+                }
+            }
+        }
+        correlations.sort_by(|x, y| y.phi.abs().partial_cmp(&x.phi.abs()).unwrap());
+        correlations.truncate(TOP_CORRELATIONS);
+
+        Stats {
+            groups: groups.len(),
+            people: people.len(),
+            questions,
+            most_common: with_count(max),
+            least_common: with_count(min),
+            group_sizes,
+            unanimous_groups: groups.iter().filter(|g| g.everyone() == g.anyone()).count(),
+            correlations,
+        }
+    }
+}
+
+fn chars(list: &[char]) -> String {
+    list.iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "groups            {}", self.groups)?;
+        writeln!(f, "people            {}", self.people)?;
+        writeln!(f, "unanimous groups  {}", self.unanimous_groups)?;
+        writeln!(f, "most common       {}", chars(&self.most_common))?;
+        writeln!(f, "least common      {}", chars(&self.least_common))?;
+        writeln!(f)?;
+        writeln!(f, "question    yes   share")?;
+        for q in &self.questions {
+            writeln!(
+                f,
+                "{:<8} {:>6} {:>6.1}%",
+                q.question,
+                q.yes,
+                q.share * 100.0
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(f, "group size  groups")?;
+        for (size, n) in &self.group_sizes {
+            writeln!(f, "{:<10} {:>7}", size, n)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "questions   phi")?;
+        for c in &self.correlations {
+            writeln!(f, "{} {}      {:>7.3}", c.a, c.b, c.phi)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read_groups;
+
+    #[test]
+    fn test_stats() {
+        let groups = read_groups("abc\n\na\nb\nc\n\nab\nac\n\na\na\na\na\n\nb".as_bytes()).unwrap();
+        let stats = Stats::new(&groups);
+        assert_eq!(stats.groups, 5);
+        assert_eq!(stats.people, 11);
+        assert_eq!(stats.most_common, vec!['a']);
+        assert_eq!(stats.least_common.len(), 23);
+        assert_eq!(
+            stats.questions[0],
+            QuestionStat {
+                question: 'a',
+                yes: 8,
+                share: 8.0 / 11.0
+            }
+        );
+        assert_eq!(stats.questions[1].question, 'b');
+        assert_eq!(stats.questions[2].question, 'c');
+        let sizes = stats.group_sizes.into_iter().collect::<Vec<_>>();
+        assert_eq!(sizes, vec![(1, 2), (2, 1), (3, 1), (4, 1)]);
+        // abc, a a a a and b
+        assert_eq!(stats.unanimous_groups, 3);
+        assert_eq!(stats.correlations.len(), 3);
+    }
+
+    #[test]
+    fn test_phi() {
+        let people = ["ab", "ab", "", ""]
+            .iter()
+            .map(|line| Answers::parse(line).unwrap())
+            .collect::<Vec<Answers>>();
+        assert_eq!(phi(&people, 0, 1), Some(1.0));
+        let people = ["a", "b", "a", "b"]
+            .iter()
+            .map(|line| Answers::parse(line).unwrap())
+            .collect::<Vec<Answers>>();
+        assert_eq!(phi(&people, 0, 1), Some(-1.0));
+        assert_eq!(phi(&people, 0, 2), None);
+    }
+}
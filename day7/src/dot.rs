@@ -0,0 +1,178 @@
+use crate::graph::BagGraph;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::{Direction, Incoming, Outgoing};
+use std::fmt::Write;
+
+const FOCUS_COLOR: &str = "gold";
+const ANCESTOR_COLOR: &str = "lightblue";
+const DESCENDANT_COLOR: &str = "lightpink";
+
+#[derive(Debug, Default)]
+pub struct DotOptions {
+    // highlight every bag that can hold this one
+    pub ancestors: Option<String>,
+    // highlight every bag this one holds
+    pub descendants: Option<String>,
+    // only highlight bags at most this many edges away
+    pub depth: Option<usize>,
+}
+
+impl DotOptions {
+    // parses --ancestors <color>, --descendants <color> and --depth <n>
+    pub fn parse(args: &[String]) -> Result<DotOptions, String> {
+        let mut options = DotOptions::default();
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let mut value = || {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| format!("{} needs a value", flag))
+            };
+            match flag.as_str() {
+                "--ancestors" => options.ancestors = Some(value()?),
+                "--descendants" => options.descendants = Some(value()?),
+                "--depth" => {
+                    let depth = value()?;
+                    let depth = depth
+                        .parse()
+                        .map_err(|_| format!("invalid depth '{}'", depth))?;
+                    options.depth = Some(depth);
+                }
+                _ => return Err(format!("unknown option '{}'", flag)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+// color names may contain anything but line breaks, only quotes and backslashes need escaping
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// fill color for each node, None if it is not highlighted
+fn highlights(bags: &BagGraph, options: &DotOptions) -> Result<Vec<Option<&'static str>>, String> {
+    let mut fill = vec![None; bags.graph.node_count()];
+    let queries: [(&Option<String>, Direction, &'static str); 2] = [
+        (&options.ancestors, Incoming, ANCESTOR_COLOR),
+        (&options.descendants, Outgoing, DESCENDANT_COLOR),
+    ];
+    let mut focus = Vec::new();
+    for (color, direction, fill_color) in queries.iter() {
+        let color = match color {
+            Some(color) => color,
+            None => continue,
+        };
+        let start = bags
+            .node(color)
+            .ok_or_else(|| format!("unknown bag color '{}'", color))?;
+        for (node, _) in bags.reachable(start, *direction, options.depth) {
+            fill[node.index()] = Some(*fill_color);
+        }
+        focus.push(start);
+    }
+    for node in focus {
+        fill[node.index()] = Some(FOCUS_COLOR);
+    }
+    Ok(fill)
+}
+
+// renders the bags as a digraph, parent -> child edges are labelled with the count
+pub fn render(bags: &BagGraph, options: &DotOptions) -> Result<String, String> {
+    let fill = highlights(bags, options)?;
+    let id = |node: NodeIndex| format!("n{}", node.index());
+    let mut dot = String::new();
+    writeln!(dot, "digraph bags {{").unwrap();
+    writeln!(dot, "    node [shape=box];").unwrap();
+    for node in bags.graph.node_indices() {
+        write!(dot, "    {} [label={}", id(node), quote(&bags.graph[node])).unwrap();
+        if let Some(color) = fill[node.index()] {
+            write!(dot, ", style=filled, fillcolor={}", color).unwrap();
+        }
+        writeln!(dot, "];").unwrap();
+    }
+    // oldest edge first so the output follows the rule order
+    let mut edges = bags.graph.edge_references().collect::<Vec<_>>();
+    edges.sort_by_key(|edge| edge.id());
+    for edge in edges {
+        let (parent, child) = (edge.source(), edge.target());
+        write!(
+            dot,
+            "    {} -> {} [label={}",
+            id(parent),
+            id(child),
+            edge.weight()
+        )
+        .unwrap();
+        if fill[parent.index()].is_some() && fill[child.index()].is_some() {
+            write!(dot, ", penwidth=2").unwrap();
+        }
+        writeln!(dot, "];").unwrap();
+    }
+    writeln!(dot, "}}").unwrap();
+    Ok(dot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::parse_rules;
+
+    fn bags() -> BagGraph {
+        let rules = parse_rules(include_str!("../test.txt").as_bytes()).unwrap();
+        BagGraph::new(&rules).unwrap()
+    }
+
+    fn node_line<'a>(dot: &'a str, color: &str) -> &'a str {
+        let label = format!("[label={}", quote(color));
+        dot.lines().find(|line| line.contains(&label)).unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        let dot = render(&bags(), &DotOptions::default()).unwrap();
+        assert!(dot.starts_with("digraph bags {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert_eq!(dot.matches(" -> ").count(), 13);
+        assert!(!dot.contains("filled"));
+        assert_eq!(
+            node_line(&dot, "light red"),
+            "    n0 [label=\"light red\"];"
+        );
+        assert!(dot.contains("    n0 -> n1 [label=1];"));
+        assert!(dot.contains("    n0 -> n2 [label=2];"));
+    }
+
+    #[test]
+    fn test_highlight() {
+        let options = DotOptions {
+            ancestors: Some("shiny gold".to_string()),
+            depth: Some(1),
+            ..DotOptions::default()
+        };
+        let dot = render(&bags(), &options).unwrap();
+        assert!(node_line(&dot, "shiny gold").contains("fillcolor=gold"));
+        assert!(node_line(&dot, "bright white").contains("fillcolor=lightblue"));
+        assert!(node_line(&dot, "muted yellow").contains("fillcolor=lightblue"));
+        // two edges away
+        assert!(!node_line(&dot, "light red").contains("filled"));
+        assert_eq!(dot.matches("penwidth").count(), 2);
+
+        let options =
+            DotOptions::parse(&["--descendants".to_string(), "shiny gold".to_string()]).unwrap();
+        let dot = render(&bags(), &options).unwrap();
+        assert!(node_line(&dot, "dotted black").contains("fillcolor=lightpink"));
+        assert!(!node_line(&dot, "bright white").contains("filled"));
+
+        let options = DotOptions::parse(&["--ancestors".to_string(), "plaid".to_string()]).unwrap();
+        assert!(render(&bags(), &options).is_err());
+        assert!(DotOptions::parse(&["--depth".to_string(), "x".to_string()]).is_err());
+        assert!(DotOptions::parse(&["--depth".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("a \"b\" \\c"), "\"a \\\"b\\\" \\\\c\"");
+    }
+}
@@ -0,0 +1,296 @@
+use crate::rule::Rule;
+use petgraph::algo;
+use petgraph::graph::{Graph, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::{Direction, Incoming, Outgoing};
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum GraphError {
+    // starts and ends with the same color
+    Cycle(Vec<String>),
+    // the number of bags inside this color does not fit in a usize
+    Overflow(String),
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::Cycle(cycle) => {
+                write!(f, "bag rules contain a cycle: {}", cycle.join(" -> "))
+            }
+            GraphError::Overflow(color) => write!(f, "too many bags inside {}", color),
+        }
+    }
+}
+
+impl Error for GraphError {}
+
+// bags are nodes, an edge parent -> child holds how many child bags the parent contains
+pub struct BagGraph {
+    pub graph: Graph<String, usize>,
+    index: HashMap<String, NodeIndex>,
+    // total_inside for every node, indexed by NodeIndex
+    totals: Vec<usize>,
+}
+
+impl BagGraph {
+    pub fn new(rules: &[Rule]) -> Result<BagGraph, GraphError> {
+        let mut bags = BagGraph {
+            graph: Graph::new(),
+            index: HashMap::new(),
+            totals: Vec::new(),
+        };
+        for rule in rules {
+            let parent = bags.find_or_add(&rule.color);
+            for (count, color) in &rule.contents {
+                let child = bags.find_or_add(color);
+                bags.graph.add_edge(parent, child, *count);
+            }
+        }
+        let order =
+            algo::toposort(&bags.graph, None).map_err(|cycle| bags.find_cycle(cycle.node_id()))?;
+
+        // children come after their parents in topological order, so walking it
+        // backwards sees every child's total before it is needed
+        bags.totals = vec![0; bags.graph.node_count()];
+        for node in order.into_iter().rev() {
+            let mut total = Some(0usize);
+            for edge in bags.graph.edges_directed(node, Outgoing) {
+                let inside = bags.totals[edge.target().index()]
+                    .checked_add(1)
+                    .and_then(|n| n.checked_mul(*edge.weight()));
+                total = total.zip(inside).and_then(|(a, b)| a.checked_add(b));
+            }
+            bags.totals[node.index()] =
+                total.ok_or_else(|| GraphError::Overflow(bags.graph[node].clone()))?;
+        }
+        Ok(bags)
+    }
+
+    // depth first search for a path from start back to itself
+    fn find_cycle(&self, start: NodeIndex) -> GraphError {
+        let mut path = vec![start];
+        let mut visited = vec![false; self.graph.node_count()];
+        let mut stack = vec![self.graph.neighbors_directed(start, Outgoing).detach()];
+        while let Some(neighbors) = stack.last_mut() {
+            match neighbors.next_node(&self.graph) {
+                Some(next) if next == start => {
+                    path.push(start);
+                    break;
+                }
+                Some(next) if !visited[next.index()] => {
+                    visited[next.index()] = true;
+                    path.push(next);
+                    stack.push(self.graph.neighbors_directed(next, Outgoing).detach());
+                }
+                Some(_) => {}
+                None => {
+                    stack.pop();
+                    path.pop();
+                }
+            }
+        }
+        GraphError::Cycle(path.iter().map(|id| self.graph[*id].clone()).collect())
+    }
+
+    fn find_or_add(&mut self, color: &str) -> NodeIndex {
+        if let Some(id) = self.index.get(color) {
+            return *id;
+        }
+        let id = self.graph.add_node(color.to_string());
+        self.index.insert(color.to_string(), id);
+        id
+    }
+
+    pub fn node(&self, color: &str) -> Option<NodeIndex> {
+        self.index.get(color).copied()
+    }
+
+    // every bag that eventually contains color, found by one breadth first
+    // search against the edge direction
+    pub fn containers_of(&self, color: &str) -> Option<Vec<&str>> {
+        let target = self.node(color)?;
+        Some(
+            self.reachable(target, Incoming, None)
+                .into_iter()
+                .map(|(node, _)| self.graph[node].as_str())
+                .collect(),
+        )
+    }
+
+    // breadth first search from start following edges in direction, stopping after
+    // max_depth levels, returns the nodes found with their distance from start
+    pub fn reachable(
+        &self,
+        start: NodeIndex,
+        direction: Direction,
+        max_depth: Option<usize>,
+    ) -> Vec<(NodeIndex, usize)> {
+        let mut seen = vec![false; self.graph.node_count()];
+        let mut queue = VecDeque::new();
+        let mut found = Vec::new();
+        seen[start.index()] = true;
+        queue.push_back((start, 0));
+        while let Some((node, depth)) = queue.pop_front() {
+            if max_depth.is_some_and(|max| depth >= max) {
+                continue;
+            }
+            for next in self.graph.neighbors_directed(node, direction) {
+                if !seen[next.index()] {
+                    seen[next.index()] = true;
+                    found.push((next, depth + 1));
+                    queue.push_back((next, depth + 1));
+                }
+            }
+        }
+        found
+    }
+
+    // the bags directly inside color, with their counts
+    pub fn contents_of(&self, color: &str) -> Option<Vec<(usize, &str)>> {
+        let node = self.node(color)?;
+        let mut contents = self
+            .graph
+            .edges_directed(node, Outgoing)
+            .map(|edge| (*edge.weight(), self.graph[edge.target()].as_str()))
+            .collect::<Vec<(usize, &str)>>();
+        // petgraph lists the newest edge first
+        contents.reverse();
+        Some(contents)
+    }
+
+    // how many bags color holds in total, not counting itself
+    pub fn total_inside(&self, color: &str) -> Option<usize> {
+        Some(self.totals[self.node(color)?.index()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::parse_rules;
+
+    fn bags(input: &str) -> BagGraph {
+        BagGraph::new(&parse_rules(input.as_bytes()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_containers_of() {
+        let bags = bags(include_str!("../test.txt"));
+        let mut containers = bags.containers_of("shiny gold").unwrap();
+        containers.sort_unstable();
+        assert_eq!(
+            containers,
+            vec!["bright white", "dark orange", "light red", "muted yellow"]
+        );
+        assert_eq!(bags.containers_of("light red"), Some(vec![]));
+        assert_eq!(bags.containers_of("plaid purple"), None);
+    }
+
+    #[test]
+    fn test_contents_of() {
+        let bags = bags(include_str!("../test.txt"));
+        assert_eq!(
+            bags.contents_of("muted yellow"),
+            Some(vec![(2, "shiny gold"), (9, "faded blue")])
+        );
+        assert_eq!(bags.contents_of("faded blue"), Some(vec![]));
+    }
+
+    #[test]
+    fn test_total_inside() {
+        assert_eq!(
+            bags(include_str!("../test.txt")).total_inside("shiny gold"),
+            Some(32)
+        );
+        let bags = bags(include_str!("../test2.txt"));
+        assert_eq!(bags.total_inside("shiny gold"), Some(126));
+        assert_eq!(bags.total_inside("dark violet"), Some(0));
+        assert_eq!(bags.total_inside("plaid purple"), None);
+    }
+
+    #[test]
+    fn test_exact_colors() {
+        let bags = bags(
+            "dark red orange bags contain 2 dark red bags.
+dark red bags contain 3 faded blue bags.
+faded blue bags contain no other bags.",
+        );
+        assert_eq!(bags.graph.node_count(), 3);
+        assert_eq!(bags.total_inside("dark red"), Some(3));
+        assert_eq!(bags.total_inside("dark red orange"), Some(8));
+        assert_eq!(
+            bags.containers_of("dark red"),
+            Some(vec!["dark red orange"])
+        );
+    }
+
+    #[test]
+    fn test_shared_bags() {
+        // every level doubles the paths to the bottom, walking them one at a time would take 2^60 steps
+        let mut input = String::new();
+        for i in 0..60 {
+            input.push_str(&format!(
+                "level{} bags contain 1 left{} bag, 1 right{} bag.\n",
+                i, i, i
+            ));
+            input.push_str(&format!("left{} bags contain 1 level{} bag.\n", i, i + 1));
+            input.push_str(&format!("right{} bags contain 1 level{} bag.\n", i, i + 1));
+        }
+        input.push_str("level60 bags contain no other bags.\n");
+        let bags = bags(&input);
+        assert_eq!(bags.total_inside("level59"), Some(4));
+        assert_eq!(bags.total_inside("level0"), Some(4 * ((1 << 60) - 1)));
+        assert_eq!(bags.containers_of("level60").unwrap().len(), 180);
+    }
+
+    #[test]
+    fn test_cycle() {
+        let rules = parse_rules(
+            "faded blue bags contain 1 dark red bag.
+dark red bags contain 2 light red bags, 1 dotted black bag.
+light red bags contain 3 dark red bags.
+dotted black bags contain no other bags."
+                .as_bytes(),
+        )
+        .unwrap();
+        let cycle = match BagGraph::new(&rules).err().unwrap() {
+            GraphError::Cycle(cycle) => cycle,
+            error => panic!("unexpected error {:?}", error),
+        };
+        assert_eq!(cycle.first(), cycle.last());
+        assert_eq!(cycle.len(), 3);
+        assert!(cycle.contains(&"dark red".to_string()));
+        assert!(cycle.contains(&"light red".to_string()));
+
+        let rules = parse_rules("red bags contain 1 red bag.".as_bytes()).unwrap();
+        assert_eq!(
+            BagGraph::new(&rules).err().unwrap().to_string(),
+            "bag rules contain a cycle: red -> red"
+        );
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut input = String::new();
+        for i in 0..4 {
+            input.push_str(&format!(
+                "level{} bags contain 4000000 level{} bags.\n",
+                i,
+                i + 1
+            ));
+        }
+        input.push_str("level4 bags contain no other bags.\n");
+        let rules = parse_rules(input.as_bytes()).unwrap();
+        assert_eq!(
+            BagGraph::new(&rules).err(),
+            Some(GraphError::Overflow("level1".to_string()))
+        );
+        // the levels below still fit
+        let bags = bags(&input[input.find("level2 bags contain").unwrap()..]);
+        assert_eq!(bags.total_inside("level2"), Some(16000004000000));
+    }
+}
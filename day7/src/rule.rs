@@ -0,0 +1,188 @@
+use std::error::Error;
+use std::fmt;
+use std::io::BufRead;
+use std::str::FromStr;
+
+// rule := color " bags contain " contents "."
+// contents := "no other bags" | item (", " item)*
+// item := count " " color (" bag" | " bags")
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub color: String,
+    pub contents: Vec<(usize, String)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+const CONTAIN: &str = " bags contain ";
+const EMPTY: &str = "no other bags";
+
+fn parse_color(color: &str) -> Result<String, String> {
+    if color.is_empty() {
+        return Err("missing color".to_string());
+    }
+    if color.split(' ').any(|word| word.is_empty()) {
+        return Err(format!("malformed color '{}'", color));
+    }
+    Ok(color.to_string())
+}
+
+fn parse_item(item: &str) -> Result<(usize, String), String> {
+    let (count, rest) = match item.find(' ') {
+        Some(i) => (&item[..i], &item[i + 1..]),
+        None => return Err(format!("expected '<count> <color> bags', got '{}'", item)),
+    };
+    let count = count
+        .parse::<usize>()
+        .map_err(|_| format!("invalid count '{}' in '{}'", count, item))?;
+    if count == 0 {
+        return Err(format!("count must be positive in '{}'", item));
+    }
+    let color = match (rest.strip_suffix(" bags"), rest.strip_suffix(" bag")) {
+        (Some(color), _) if count != 1 => color,
+        (_, Some(color)) if count == 1 => color,
+        _ => {
+            let expected = if count == 1 { "bag" } else { "bags" };
+            return Err(format!("expected '{}' after color in '{}'", expected, item));
+        }
+    };
+    Ok((count, parse_color(color)?))
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        let body = line
+            .strip_suffix('.')
+            .ok_or_else(|| "rule must end with '.'".to_string())?;
+        let (color, contents) = match body.find(CONTAIN) {
+            Some(i) => (&body[..i], &body[i + CONTAIN.len()..]),
+            None => return Err(format!("expected '{}'", CONTAIN.trim())),
+        };
+        let color = parse_color(color)?;
+        let contents = match contents {
+            EMPTY => Vec::new(),
+            _ => contents
+                .split(", ")
+                .map(parse_item)
+                .collect::<Result<Vec<(usize, String)>, String>>()?,
+        };
+        Ok(Rule { color, contents })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.color, CONTAIN)?;
+        if self.contents.is_empty() {
+            return write!(f, "{}.", EMPTY);
+        }
+        for (i, (count, color)) in self.contents.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            match count {
+                1 => write!(f, "1 {} bag", color)?,
+                n => write!(f, "{} {} bags", n, color)?,
+            }
+        }
+        write!(f, ".")
+    }
+}
+
+// parses one rule per line, blank lines are skipped
+pub fn parse_rules<R: BufRead>(reader: R) -> Result<Vec<Rule>, Box<dyn Error>> {
+    let mut rules = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let rule = line.parse::<Rule>().map_err(|message| ParseError {
+            line: i + 1,
+            message,
+        })?;
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rule() {
+        assert_eq!(
+            "light red bags contain 1 bright white bag, 2 muted yellow bags.".parse(),
+            Ok(Rule {
+                color: "light red".to_string(),
+                contents: vec![
+                    (1, "bright white".to_string()),
+                    (2, "muted yellow".to_string())
+                ],
+            })
+        );
+        assert_eq!(
+            "faded blue bags contain no other bags.".parse(),
+            Ok(Rule {
+                color: "faded blue".to_string(),
+                contents: vec![],
+            })
+        );
+        // words that used to confuse the word-by-word parser
+        assert_eq!(
+            "baggy contained bags contain 3 dark baggage bags.".parse(),
+            Ok(Rule {
+                color: "baggy contained".to_string(),
+                contents: vec![(3, "dark baggage".to_string())],
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let invalid = [
+            "light red bags contain 1 bright white bag",
+            "light red bags hold 1 bright white bag.",
+            "light red bags contain one bright white bag.",
+            "light red bags contain 0 bright white bags.",
+            "light red bags contain 2 bright white bag.",
+            "light red bags contain 2 bright white.",
+            " bags contain no other bags.",
+            "light  red bags contain no other bags.",
+        ];
+        for line in invalid.iter() {
+            assert!(line.parse::<Rule>().is_err(), "{}", line);
+        }
+
+        let input =
+            "faded blue bags contain no other bags.\n\nlight red bags contain 1 bright white bag";
+        let error = parse_rules(input.as_bytes()).unwrap_err();
+        assert_eq!(error.to_string(), "line 3: rule must end with '.'");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input = include_str!("../test.txt");
+        let rules = parse_rules(input.as_bytes()).unwrap();
+        assert_eq!(rules.len(), 9);
+        for (rule, line) in rules.iter().zip(input.lines()) {
+            assert_eq!(rule.to_string(), line);
+        }
+    }
+}
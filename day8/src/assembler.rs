@@ -0,0 +1,227 @@
+use crate::instruction::{lookup, Instruction, Operand, ParseError};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write;
+use std::io::BufRead;
+
+// source := (line "\n")*
+// line := [label ":"] [opcode [operand]] [comment]
+// comment := ("#" | ";") anything
+// operands of jumps may name a label instead of an offset, plain boot code
+// with one instruction per line is valid source as it is
+pub fn assemble<R: BufRead>(reader: R) -> Result<Vec<Instruction>, Box<dyn Error>> {
+    // the first pass finds the labels, the second one decodes the instructions
+    let mut statements = Vec::new();
+    let mut labels = HashMap::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let error = |message: String| ParseError {
+            line: i + 1,
+            message,
+        };
+        let code = match line.find(['#', ';']) {
+            Some(start) => &line[..start],
+            None => &line[..],
+        };
+        let code = match code.find(':') {
+            Some(colon) => {
+                let label = code[..colon].trim();
+                if !is_label(label) {
+                    return Err(error(format!("invalid label '{}'", label)).into());
+                }
+                if labels.insert(label.to_string(), statements.len()).is_some() {
+                    return Err(error(format!("duplicate label '{}'", label)).into());
+                }
+                &code[colon + 1..]
+            }
+            None => code,
+        };
+        let words = code.split_whitespace().collect::<Vec<&str>>();
+        match words.len() {
+            0 => {}
+            1 | 2 => statements.push((
+                i + 1,
+                words[0].to_string(),
+                words.get(1).map(|w| w.to_string()),
+            )),
+            _ => {
+                return Err(error(format!(
+                    "expected '<opcode> [operand]', got '{}'",
+                    code.trim()
+                ))
+                .into())
+            }
+        }
+    }
+
+    let mut rom = Vec::with_capacity(statements.len());
+    for (index, (line, name, operand)) in statements.into_iter().enumerate() {
+        let error = |message: String| ParseError { line, message };
+        let opcode = lookup(&name).ok_or_else(|| error(format!("unknown opcode '{}'", name)))?;
+        let operand = match operand {
+            Some(label) if opcode.operand == Operand::Offset && is_label(&label) => {
+                let target = labels
+                    .get(&label)
+                    .ok_or_else(|| error(format!("unknown label '{}'", label)))?;
+                Some(format!("{:+}", *target as i64 - index as i64))
+            }
+            operand => operand,
+        };
+        rom.push(opcode.decode(operand.as_deref()).map_err(error)?);
+    }
+    Ok(rom)
+}
+
+fn is_label(word: &str) -> bool {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+// source that assembles back to rom, jumps that stay inside the program or land
+// right after it get a label
+pub fn disassemble(rom: &[Instruction]) -> String {
+    let target = |index: usize, instruction: Instruction| {
+        let offset = instruction.offset()?;
+        let target = index as i64 + offset as i64;
+        match target >= 0 && target as usize <= rom.len() {
+            true => Some(target as usize),
+            false => None,
+        }
+    };
+    let mut labelled = vec![false; rom.len() + 1];
+    for (index, instruction) in rom.iter().enumerate() {
+        if let Some(target) = target(index, *instruction) {
+            labelled[target] = true;
+        }
+    }
+
+    let mut source = String::new();
+    for index in 0..=rom.len() {
+        if labelled[index] {
+            writeln!(source, "L{}:", index).unwrap();
+        }
+        if index == rom.len() {
+            break;
+        }
+        let instruction = rom[index];
+        match target(index, instruction) {
+            Some(target) => writeln!(source, "    {} L{}", instruction.opcode(), target),
+            None => writeln!(source, "    {}", instruction),
+        }
+        .unwrap();
+    }
+    source
+}
+
+// assembles source known to be valid, for tests
+#[cfg(test)]
+pub fn assemble_str(source: &str) -> Vec<Instruction> {
+    assemble(source.as_bytes()).unwrap()
+}
+
+// the boot code from the puzzle example
+#[cfg(test)]
+pub fn example() -> Vec<Instruction> {
+    assemble_str(include_str!("../test.txt"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boot_code() {
+        let input = include_str!("../test.txt");
+        let rom = assemble(input.as_bytes()).unwrap();
+        assert_eq!(rom.len(), 9);
+        for (instruction, line) in rom.iter().zip(input.lines()) {
+            assert_eq!(instruction.to_string(), line);
+        }
+
+        let error = assemble("nop +0\n\nfoo +1\n".as_bytes()).unwrap_err();
+        assert_eq!(error.to_string(), "line 3: unknown opcode 'foo'");
+    }
+
+    #[test]
+    fn test_labels_and_comments() {
+        let source = "
+# prints 3, 2, 1
+        acc +3
+loop:   jez done    ; leave once acc is 0
+        out
+        st r0
+        ld r1       # r1 is never written, so 0
+        acc -1
+        add r0
+        jmp loop
+done:   hlt
+";
+        assert_eq!(
+            assemble(source.as_bytes()).unwrap(),
+            vec![
+                Instruction::Acc(3),
+                Instruction::Jez(7),
+                Instruction::Out,
+                Instruction::St(0),
+                Instruction::Ld(1),
+                Instruction::Acc(-1),
+                Instruction::Add(0),
+                Instruction::Jmp(-6),
+                Instruction::Hlt,
+            ]
+        );
+
+        let errors = [
+            ("jmp nowhere", "line 1: unknown label 'nowhere'"),
+            ("a: nop +0\na: nop +0", "line 2: duplicate label 'a'"),
+            ("1a: nop +0", "line 1: invalid label '1a'"),
+            (
+                "acc start\nstart: hlt",
+                "line 1: argument 'start' must start with '+' or '-'",
+            ),
+            (
+                "acc +1 +2",
+                "line 1: expected '<opcode> [operand]', got 'acc +1 +2'",
+            ),
+        ];
+        for (source, message) in errors.iter() {
+            assert_eq!(
+                assemble(source.as_bytes()).unwrap_err().to_string(),
+                *message
+            );
+        }
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let rom = example();
+        let source = disassemble(&rom);
+        assert_eq!(
+            source,
+            "    nop +0
+L1:
+    acc +1
+    jmp L6
+L3:
+    acc +3
+    jmp L1
+    acc -99
+L6:
+    acc +1
+    jmp L3
+    acc +6
+"
+        );
+        assert_eq!(assemble(source.as_bytes()).unwrap(), rom);
+
+        // labels at the very end and jumps out of bounds
+        let rom = vec![Instruction::Jgz(2), Instruction::Jmp(-5)];
+        assert_eq!(disassemble(&rom), "    jgz L2\n    jmp -5\nL2:\n");
+        assert_eq!(assemble(disassemble(&rom).as_bytes()).unwrap(), rom);
+    }
+}
@@ -0,0 +1,345 @@
+use crate::instruction::Instruction;
+use std::fmt::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    Block(usize),
+    // one past the last instruction
+    End,
+    OutOfBounds(i64),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Block {
+    // instructions start..end
+    pub start: usize,
+    pub end: usize,
+    pub successors: Vec<Target>,
+}
+
+// what can be said about a program without running it, every path through the
+// graph is treated as possible even if acc would rule it out
+#[derive(Debug)]
+pub struct Analysis {
+    pub len: usize,
+    pub blocks: Vec<Block>,
+    pub unreachable: Vec<usize>,
+    // instruction and where it jumps to
+    pub out_of_bounds: Vec<(usize, i64)>,
+    // reachable instructions with no path to the end, hlt or out of bounds, once
+    // one of them runs the program never halts
+    pub trapped: Vec<usize>,
+}
+
+// where execution can continue after instruction i, nowhere after hlt
+fn successors(i: usize, instruction: Instruction) -> Vec<i64> {
+    let next = i as i64 + 1;
+    match instruction {
+        Instruction::Hlt => vec![],
+        Instruction::Jmp(n) => vec![i as i64 + n as i64],
+        _ => match instruction.offset() {
+            Some(n) if n != 1 => vec![next, i as i64 + n as i64],
+            _ => vec![next],
+        },
+    }
+}
+
+fn in_rom(target: i64, len: usize) -> Option<usize> {
+    match target >= 0 && (target as usize) < len {
+        true => Some(target as usize),
+        false => None,
+    }
+}
+
+impl Analysis {
+    pub fn new(rom: &[Instruction]) -> Analysis {
+        let len = rom.len();
+        let edges = rom
+            .iter()
+            .enumerate()
+            .map(|(i, instruction)| successors(i, *instruction))
+            .collect::<Vec<Vec<i64>>>();
+
+        let mut reachable = vec![false; len];
+        let mut stack = Vec::new();
+        if len > 0 {
+            reachable[0] = true;
+            stack.push(0);
+        }
+        while let Some(i) = stack.pop() {
+            for target in &edges[i] {
+                if let Some(next) = in_rom(*target, len) {
+                    if !reachable[next] {
+                        reachable[next] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+
+        // walk backwards from every instruction that can leave the program
+        let mut sources = vec![Vec::new(); len];
+        let mut halts = vec![false; len];
+        for (i, targets) in edges.iter().enumerate() {
+            halts[i] = targets.is_empty() || targets.iter().any(|t| in_rom(*t, len).is_none());
+            for target in targets {
+                if let Some(next) = in_rom(*target, len) {
+                    sources[next].push(i);
+                }
+            }
+        }
+        let mut stack = (0..len).filter(|i| halts[*i]).collect::<Vec<usize>>();
+        while let Some(i) = stack.pop() {
+            for source in &sources[i] {
+                if !halts[*source] {
+                    halts[*source] = true;
+                    stack.push(*source);
+                }
+            }
+        }
+
+        let out_of_bounds = edges
+            .iter()
+            .enumerate()
+            .flat_map(|(i, targets)| {
+                targets
+                    .iter()
+                    .filter(|t| **t < 0 || **t as usize > len)
+                    .map(move |t| (i, *t))
+            })
+            .collect();
+
+        Analysis {
+            len,
+            blocks: blocks(len, &edges),
+            unreachable: (0..len).filter(|i| !reachable[*i]).collect(),
+            out_of_bounds,
+            trapped: (0..len).filter(|i| reachable[*i] && !halts[*i]).collect(),
+        }
+    }
+
+    // true if every run loops forever
+    pub fn never_halts(&self) -> bool {
+        self.trapped.first() == Some(&0)
+    }
+
+    // one box per basic block, unreachable blocks are grey and trapped ones red
+    pub fn dot(&self, rom: &[Instruction]) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph cfg {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=monospace];").unwrap();
+        writeln!(dot, "    end [shape=doublecircle];").unwrap();
+        for (id, block) in self.blocks.iter().enumerate() {
+            let mut label = String::new();
+            for (i, instruction) in rom[block.start..block.end].iter().enumerate() {
+                write!(label, "{:>4}  {}\\l", block.start + i, instruction).unwrap();
+            }
+            write!(dot, "    b{} [label=\"{}\"", id, label).unwrap();
+            if self.unreachable.binary_search(&block.start).is_ok() {
+                write!(dot, ", style=dashed, color=grey").unwrap();
+            } else if self.trapped.binary_search(&block.start).is_ok() {
+                write!(dot, ", color=red").unwrap();
+            }
+            writeln!(dot, "];").unwrap();
+        }
+        for (id, block) in self.blocks.iter().enumerate() {
+            for (k, target) in block.successors.iter().enumerate() {
+                match target {
+                    Target::Block(to) => writeln!(dot, "    b{} -> b{};", id, to),
+                    Target::End => writeln!(dot, "    b{} -> end;", id),
+                    Target::OutOfBounds(pc) => writeln!(
+                        dot,
+                        "    oob{}_{} [label=\"pc {}\", shape=octagon, color=red];\n    b{} -> oob{}_{};",
+                        id, k, pc, id, id, k
+                    ),
+                }
+                .unwrap();
+            }
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+// a block starts at 0, at every jump target and after every jump or hlt
+fn blocks(len: usize, edges: &[Vec<i64>]) -> Vec<Block> {
+    let mut leader = vec![false; len + 1];
+    leader[0] = true;
+    for (i, targets) in edges.iter().enumerate() {
+        if targets.as_slice() != [i as i64 + 1] {
+            leader[i + 1] = true;
+            for target in targets {
+                if let Some(next) = in_rom(*target, len) {
+                    leader[next] = true;
+                }
+            }
+        }
+    }
+    let starts = (0..len).filter(|i| leader[*i]).collect::<Vec<usize>>();
+    let block_at = |i: usize| starts.binary_search(&i).unwrap();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(id, start)| {
+            let end = starts.get(id + 1).copied().unwrap_or(len);
+            let successors = edges[end - 1]
+                .iter()
+                .map(|target| match in_rom(*target, len) {
+                    Some(next) => Target::Block(block_at(next)),
+                    None if *target == len as i64 => Target::End,
+                    None => Target::OutOfBounds(*target),
+                })
+                .collect();
+            Block {
+                start: *start,
+                end,
+                successors,
+            }
+        })
+        .collect()
+}
+
+fn list(items: &[usize]) -> String {
+    match items.is_empty() {
+        true => "none".to_string(),
+        false => items
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<String>>()
+            .join(" "),
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Block(id) => write!(f, "b{}", id),
+            Target::End => write!(f, "end"),
+            Target::OutOfBounds(pc) => write!(f, "pc {}", pc),
+        }
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "instructions   {}", self.len)?;
+        writeln!(f, "blocks         {}", self.blocks.len())?;
+        writeln!(f, "unreachable    {}", list(&self.unreachable))?;
+        let jumps = self
+            .out_of_bounds
+            .iter()
+            .map(|(i, pc)| format!("{} -> {}", i, pc))
+            .collect::<Vec<String>>();
+        match jumps.is_empty() {
+            true => writeln!(f, "out of bounds  none")?,
+            false => writeln!(f, "out of bounds  {}", jumps.join(", "))?,
+        }
+        writeln!(f, "trapped        {}", list(&self.trapped))?;
+        if self.never_halts() {
+            writeln!(f, "every run is an infinite loop")?;
+        }
+        writeln!(f)?;
+        writeln!(f, "block   instructions  successors")?;
+        for (id, block) in self.blocks.iter().enumerate() {
+            let successors = block
+                .successors
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<String>>();
+            writeln!(
+                f,
+                "{:<7} {:>5}..{:<5}  {}",
+                format!("b{}", id),
+                block.start,
+                block.end,
+                match successors.is_empty() {
+                    true => "halt".to_string(),
+                    false => successors.join(" "),
+                }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::{assemble_str, example};
+
+    #[test]
+    fn test_boot_code() {
+        let analysis = Analysis::new(&example());
+        assert_eq!(analysis.unreachable, vec![5, 8]);
+        assert!(analysis.out_of_bounds.is_empty());
+        assert_eq!(analysis.trapped, vec![0, 1, 2, 3, 4, 6, 7]);
+        assert!(analysis.never_halts());
+        let starts = analysis
+            .blocks
+            .iter()
+            .map(|block| (block.start, block.end))
+            .collect::<Vec<(usize, usize)>>();
+        assert_eq!(starts, vec![(0, 1), (1, 3), (3, 5), (5, 6), (6, 8), (8, 9)]);
+        assert_eq!(analysis.blocks[1].successors, vec![Target::Block(4)]);
+        assert_eq!(analysis.blocks[5].successors, vec![Target::End]);
+
+        let mut fixed = example();
+        fixed[7] = fixed[7].swapped();
+        let analysis = Analysis::new(&fixed);
+        assert_eq!(analysis.unreachable, vec![3, 4, 5]);
+        assert!(analysis.trapped.is_empty());
+    }
+
+    #[test]
+    fn test_extended() {
+        let analysis = Analysis::new(&assemble_str(
+            "acc -2
+jlz +3
+hlt
+jmp -1
+acc +1
+jmp -20
+jez -1",
+        ));
+        assert_eq!(analysis.unreachable, vec![3, 6]);
+        assert_eq!(analysis.out_of_bounds, vec![(5, -15)]);
+        assert!(analysis.trapped.is_empty());
+        assert!(!analysis.never_halts());
+        assert_eq!(
+            analysis.blocks[0].successors,
+            vec![Target::Block(1), Target::Block(3)]
+        );
+        assert_eq!(analysis.blocks[1].successors, vec![]);
+
+        // jez can keep spinning, but leaves as soon as acc is not zero
+        let analysis = Analysis::new(&assemble_str("jez +0\nhlt"));
+        assert!(analysis.trapped.is_empty());
+        let analysis = Analysis::new(&assemble_str("jgz +2\njmp -1\njmp -2"));
+        assert_eq!(analysis.trapped, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_output() {
+        let rom = assemble_str("acc +1\njmp +2\nnop +0\njmp -4");
+        let analysis = Analysis::new(&rom);
+        assert_eq!(
+            analysis.to_string(),
+            "instructions   4
+blocks         3
+unreachable    2
+out of bounds  3 -> -1
+trapped        none
+
+block   instructions  successors
+b0          0..2      b2
+b1          2..3      b2
+b2          3..4      pc -1
+"
+        );
+        let dot = analysis.dot(&rom);
+        assert!(dot.contains("    b0 [label=\"   0  acc +1\\l   1  jmp +2\\l\"];"));
+        assert!(dot.contains("    b1 [label=\"   2  nop +0\\l\", style=dashed, color=grey];"));
+        assert!(dot.contains("    b2 -> oob2_0;"));
+        assert!(dot.contains("    b0 -> b2;"));
+    }
+}
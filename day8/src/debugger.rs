@@ -0,0 +1,518 @@
+use crate::instruction::{lookup, Instruction};
+use crate::program::{Outcome, Program, Undo, STEP_LIMIT};
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "commands:
+  s, step [n]          execute n instructions, default 1
+  rs, reverse [n]      undo the last n instructions, default 1
+  c, continue          run until a breakpoint, a watch, a loop, the end or 1000000 steps
+  b, break <pc|op>     break before instruction pc or before any instruction with opcode op
+  d, delete <pc|op>    remove a breakpoint
+  breakpoints          list breakpoints
+  w, watch             toggle stopping whenever acc changes
+  l, list [n]          show n instructions around pc, default 3
+  t, trace [n]         show the last n executed instructions, default 10
+  p, print             show pc and acc
+  o, output            show every value written by out
+  r, reset             restart the program and clear its history
+  h, help              show this help
+  q, quit              leave the debugger";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Breakpoint {
+    Pc(usize),
+    Opcode(String),
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Pc(pc) => write!(f, "pc {}", pc),
+            Breakpoint::Opcode(opcode) => write!(f, "{}", opcode),
+        }
+    }
+}
+
+impl Breakpoint {
+    fn parse(arg: &str) -> Result<Breakpoint, String> {
+        match arg {
+            _ if lookup(arg).is_some() => Ok(Breakpoint::Opcode(arg.to_string())),
+            _ => arg
+                .parse()
+                .map(Breakpoint::Pc)
+                .map_err(|_| format!("expected an instruction index or opcode, got '{}'", arg)),
+        }
+    }
+
+    fn matches(&self, pc: usize, instruction: Instruction) -> bool {
+        match self {
+            Breakpoint::Pc(at) => *at == pc,
+            Breakpoint::Opcode(opcode) => opcode == instruction.opcode(),
+        }
+    }
+}
+
+pub struct Debugger<'a> {
+    rom: &'a [Instruction],
+    // without conditional jumps a program loops as soon as an instruction runs
+    // twice, otherwise only once the whole state repeats
+    independent: bool,
+    program: Program,
+    breakpoints: Vec<Breakpoint>,
+    watch: bool,
+    // how to take back each executed instruction, newest last
+    history: Vec<Undo>,
+    // how often each instruction is in history, to spot loops
+    executed: Vec<usize>,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(rom: &'a [Instruction]) -> Debugger<'a> {
+        Debugger {
+            rom,
+            independent: rom
+                .iter()
+                .all(|instruction| instruction.is_acc_independent()),
+            program: Program::new(),
+            breakpoints: Vec::new(),
+            watch: false,
+            history: Vec::new(),
+            executed: vec![0; rom.len()],
+        }
+    }
+
+    // the instruction at pc, None once the program has halted
+    fn current(&self) -> Option<(usize, Instruction)> {
+        match self.program.halted(self.rom) {
+            Some(_) => None,
+            None => {
+                let pc = self.program.pc() as usize;
+                Some((pc, self.rom[pc]))
+            }
+        }
+    }
+
+    fn halted(&self) -> String {
+        match self.program.halted(self.rom) {
+            Some(Outcome::OutOfBounds { pc }) => {
+                format!("pc {} out of bounds, acc {}", pc, self.program.get_acc())
+            }
+            Some(Outcome::Overflow { pc }) => {
+                format!(
+                    "overflow at instruction {}, acc {}",
+                    pc,
+                    self.program.get_acc()
+                )
+            }
+            _ => format!("terminated, acc {}", self.program.get_acc()),
+        }
+    }
+
+    // executes one instruction, Err if the program has halted
+    fn step_once(&mut self) -> Result<(), String> {
+        let (pc, _) = self.current().ok_or_else(|| self.halted())?;
+        let (undo, outcome) = self.program.step(self.rom);
+        self.history.extend(undo);
+        self.executed[pc] += 1;
+        match outcome {
+            Some(_) => Err(self.halted()),
+            None => Ok(()),
+        }
+    }
+
+    fn reverse_once(&mut self) -> bool {
+        match self.history.pop() {
+            Some(undo) => {
+                self.executed[undo.pc as usize] -= 1;
+                self.program.undo(undo);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn location(&self) -> String {
+        match self.current() {
+            Some((pc, instruction)) => {
+                format!("pc {}: {}, acc {}", pc, instruction, self.program.get_acc())
+            }
+            None => self.halted(),
+        }
+    }
+
+    // steps at least once, then stops before a breakpoint, before the program loops,
+    // after acc changes while watched, when the program halts or after STEP_LIMIT
+    // steps
+    fn resume(&mut self) -> String {
+        let mut states = match self.independent {
+            true => HashSet::new(),
+            false => {
+                // the states before each step, rebuilt walking back from the current one
+                let (_, _, mut registers) = self.program.state();
+                self.history
+                    .iter()
+                    .rev()
+                    .map(|undo| {
+                        if let Some((r, value)) = undo.register {
+                            registers[r as usize] = value;
+                        }
+                        (undo.pc, undo.acc, registers)
+                    })
+                    .collect()
+            }
+        };
+        for _ in 0..STEP_LIMIT {
+            let acc = self.program.get_acc();
+            if !self.independent {
+                states.insert(self.program.state());
+            }
+            if let Err(halted) = self.step_once() {
+                return halted;
+            }
+            if self.watch && self.program.get_acc() != acc {
+                return format!("acc changed {} -> {}", acc, self.program.get_acc());
+            }
+            let (pc, instruction) = match self.current() {
+                Some(current) => current,
+                None => return self.halted(),
+            };
+            if let Some(breakpoint) = self.breakpoints.iter().find(|b| b.matches(pc, instruction)) {
+                return format!("breakpoint {}", breakpoint);
+            }
+            if self.independent && self.executed[pc] > 0 {
+                return format!("infinite loop, instruction {} is about to run again", pc);
+            }
+            if states.contains(&self.program.state()) {
+                return format!(
+                    "infinite loop, instruction {} is about to run again with the same state",
+                    pc
+                );
+            }
+        }
+        format!("stopped after {} steps", STEP_LIMIT)
+    }
+
+    fn list(&self, radius: usize) -> Vec<String> {
+        let pc = self.program.pc();
+        let center = pc.max(0).min(self.rom.len() as i32) as usize;
+        let from = center.saturating_sub(radius);
+        let to = (center + radius + 1).min(self.rom.len());
+        (from..to)
+            .map(|i| {
+                let marker = if i as i32 == pc { '>' } else { ' ' };
+                let breakpoint = match self.breakpoints.contains(&Breakpoint::Pc(i)) {
+                    true => '*',
+                    false => ' ',
+                };
+                format!("{}{} {:>5}  {}", marker, breakpoint, i, self.rom[i])
+            })
+            .collect()
+    }
+
+    fn trace(&self, n: usize) -> Vec<String> {
+        let from = self.history.len().saturating_sub(n);
+        self.history[from..]
+            .iter()
+            .enumerate()
+            .map(|(i, before)| {
+                let pc = before.pc as usize;
+                format!(
+                    "{:>6}  {:>5}  {:<10} acc {}",
+                    from + i,
+                    pc,
+                    self.rom[pc].to_string(),
+                    before.acc
+                )
+            })
+            .collect()
+    }
+
+    // runs one command line, returns the lines to print or None to quit
+    pub fn command(&mut self, line: &str) -> Option<Vec<String>> {
+        let mut words = line.split_whitespace();
+        let name = match words.next() {
+            Some(name) => name,
+            None => return Some(vec![]),
+        };
+        let rest = words.collect::<Vec<&str>>().join(" ");
+        let count = |default: usize| -> Result<usize, String> {
+            match rest.as_str() {
+                "" => Ok(default),
+                n => n.parse().map_err(|_| format!("invalid count '{}'", n)),
+            }
+        };
+        let result = match name {
+            "s" | "step" => count(1).map(|n| {
+                for _ in 0..n {
+                    if let Err(halted) = self.step_once() {
+                        return vec![halted];
+                    }
+                }
+                vec![self.location()]
+            }),
+            "rs" | "reverse" => count(1).map(|n| {
+                let mut lines = Vec::new();
+                for _ in 0..n {
+                    if !self.reverse_once() {
+                        lines.push("at the start of the history".to_string());
+                        break;
+                    }
+                }
+                lines.push(self.location());
+                lines
+            }),
+            "c" | "continue" => {
+                let reason = self.resume();
+                match self.current() {
+                    Some(_) => Ok(vec![reason, self.location()]),
+                    None => Ok(vec![reason]),
+                }
+            }
+            "b" | "break" => Breakpoint::parse(&rest).map(|breakpoint| {
+                if !self.breakpoints.contains(&breakpoint) {
+                    self.breakpoints.push(breakpoint);
+                }
+                vec![]
+            }),
+            "d" | "delete" => Breakpoint::parse(&rest).and_then(|breakpoint| {
+                let before = self.breakpoints.len();
+                self.breakpoints.retain(|b| *b != breakpoint);
+                match self.breakpoints.len() < before {
+                    true => Ok(vec![]),
+                    false => Err(format!("no breakpoint {}", breakpoint)),
+                }
+            }),
+            "breakpoints" => Ok(self.breakpoints.iter().map(|b| b.to_string()).collect()),
+            "w" | "watch" => {
+                self.watch = !self.watch;
+                let state = if self.watch { "on" } else { "off" };
+                Ok(vec![format!("watch acc {}", state)])
+            }
+            "l" | "list" => count(3).map(|n| self.list(n)),
+            "t" | "trace" => count(10).map(|n| self.trace(n)),
+            "p" | "print" => Ok(vec![self.location()]),
+            "o" | "output" => Ok(self
+                .program
+                .output()
+                .iter()
+                .map(|n| n.to_string())
+                .collect()),
+            "r" | "reset" => {
+                self.program.reset();
+                self.history.clear();
+                self.executed.iter_mut().for_each(|n| *n = 0);
+                Ok(vec![self.location()])
+            }
+            "h" | "help" => Ok(HELP.lines().map(String::from).collect()),
+            "q" | "quit" => return None,
+            _ => Err(format!("unknown command '{}', try help", name)),
+        };
+        Some(result.unwrap_or_else(|message| vec![format!("error: {}", message)]))
+    }
+
+    // reads commands until quit or end of input
+    pub fn repl<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        writeln!(output, "{}", self.location())?;
+        write!(output, "(dbg) ")?;
+        output.flush()?;
+        for line in input.lines() {
+            match self.command(&line?) {
+                Some(lines) => {
+                    for line in lines {
+                        writeln!(output, "{}", line)?;
+                    }
+                }
+                None => return Ok(()),
+            }
+            write!(output, "(dbg) ")?;
+            output.flush()?;
+        }
+        writeln!(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::{assemble_str, example};
+
+    #[test]
+    fn test_step_and_reverse() {
+        let rom = example();
+        let mut debugger = Debugger::new(&rom);
+        assert_eq!(
+            debugger.command("step 3"),
+            Some(vec!["pc 6: acc +1, acc 1".to_string()])
+        );
+        assert_eq!(
+            debugger.command("rs"),
+            Some(vec!["pc 2: jmp +4, acc 1".to_string()])
+        );
+        assert_eq!(
+            debugger.command("reverse 5"),
+            Some(vec![
+                "at the start of the history".to_string(),
+                "pc 0: nop +0, acc 0".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_continue() {
+        let rom = example();
+        let mut debugger = Debugger::new(&rom);
+        assert_eq!(
+            debugger.command("c"),
+            Some(vec![
+                "infinite loop, instruction 1 is about to run again".to_string(),
+                "pc 1: acc +1, acc 5".to_string()
+            ])
+        );
+        assert_eq!(
+            debugger.trace(2),
+            vec![
+                "     5      3  acc +3     acc 2".to_string(),
+                "     6      4  jmp -3     acc 5".to_string(),
+            ]
+        );
+
+        debugger.command("reset");
+        debugger.command("break jmp");
+        debugger.command("b 7");
+        assert_eq!(
+            debugger.command("continue").unwrap()[1],
+            "pc 2: jmp +4, acc 1"
+        );
+        assert_eq!(debugger.command("continue").unwrap()[0], "breakpoint jmp");
+        debugger.command("delete jmp");
+        debugger.command("d 7");
+        debugger.command("watch");
+        assert_eq!(debugger.command("c").unwrap()[0], "acc changed 2 -> 5");
+    }
+
+    #[test]
+    fn test_halt() {
+        let rom = [Instruction::Acc(2)];
+        let mut debugger = Debugger::new(&rom);
+        assert_eq!(
+            debugger.command("s 5"),
+            Some(vec!["terminated, acc 2".to_string()])
+        );
+        assert_eq!(
+            debugger.command("c"),
+            Some(vec!["terminated, acc 2".to_string()])
+        );
+        assert_eq!(
+            debugger.command("rs"),
+            Some(vec!["pc 0: acc +2, acc 0".to_string()])
+        );
+
+        let rom = [Instruction::Jmp(-1)];
+        let mut debugger = Debugger::new(&rom);
+        assert_eq!(
+            debugger.command("c"),
+            Some(vec!["pc -1 out of bounds, acc 0".to_string()])
+        );
+
+        let rom = [Instruction::Acc(i32::MAX), Instruction::Acc(1)];
+        let mut debugger = Debugger::new(&rom);
+        assert_eq!(
+            debugger.command("s 2"),
+            Some(vec!["overflow at instruction 1, acc 2147483647".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_list_and_errors() {
+        let rom = example();
+        let mut debugger = Debugger::new(&rom);
+        debugger.command("b 1");
+        assert_eq!(
+            debugger.command("list 1"),
+            Some(vec![
+                ">      0  nop +0".to_string(),
+                " *     1  acc +1".to_string()
+            ])
+        );
+        assert_eq!(
+            debugger.command("b 1x"),
+            Some(vec![
+                "error: expected an instruction index or opcode, got '1x'".to_string()
+            ])
+        );
+        assert_eq!(
+            debugger.command("frobnicate"),
+            Some(vec![
+                "error: unknown command 'frobnicate', try help".to_string()
+            ])
+        );
+        assert_eq!(debugger.command("q"), None);
+    }
+
+    #[test]
+    fn test_repl() {
+        let rom = example();
+        let mut output = Vec::new();
+        Debugger::new(&rom)
+            .repl("s\np\nq\n".as_bytes(), &mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "pc 0: nop +0, acc 0\n(dbg) pc 1: acc +1, acc 0\n(dbg) pc 1: acc +1, acc 0\n(dbg) "
+        );
+    }
+
+    #[test]
+    fn test_extended() {
+        let rom = assemble_str("acc +2\nout\nacc -1\njgz -2\nhlt\nnop +0");
+        let mut debugger = Debugger::new(&rom);
+        debugger.command("break out");
+        assert_eq!(debugger.command("c").unwrap()[0], "breakpoint out");
+        // out runs twice without looping
+        assert_eq!(debugger.command("c").unwrap()[0], "breakpoint out");
+        assert_eq!(
+            debugger.command("c"),
+            Some(vec!["terminated, acc 0".to_string()])
+        );
+        assert_eq!(
+            debugger.command("output"),
+            Some(vec!["2".to_string(), "1".to_string()])
+        );
+        debugger.command("rs 2");
+        assert_eq!(
+            debugger.command("output"),
+            Some(vec!["2".to_string(), "1".to_string()])
+        );
+        debugger.command("rs 2");
+        assert_eq!(debugger.command("output"), Some(vec!["2".to_string()]));
+    }
+
+    #[test]
+    fn test_extended_loops() {
+        let rom = assemble_str("out\njmp -1");
+        let mut debugger = Debugger::new(&rom);
+        assert_eq!(
+            debugger.command("c").unwrap()[0],
+            "infinite loop, instruction 0 is about to run again"
+        );
+
+        // jgz sees acc 1 every time round
+        let rom = assemble_str("acc +1\nacc -1\njgz -2\nacc +1\njmp -3");
+        let mut debugger = Debugger::new(&rom);
+        assert_eq!(
+            debugger.command("c").unwrap()[0],
+            "infinite loop, instruction 1 is about to run again with the same state"
+        );
+
+        // never repeats a state, but never halts either
+        let rom = assemble_str("acc -1\njlz -1");
+        let mut debugger = Debugger::new(&rom);
+        assert_eq!(
+            debugger.command("c").unwrap()[0],
+            "stopped after 1000000 steps"
+        );
+        assert_eq!(debugger.history.len(), STEP_LIMIT);
+    }
+}
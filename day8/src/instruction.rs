@@ -0,0 +1,295 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+pub const REGISTERS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    Nop(i32),
+    Acc(i32),
+    Jmp(i32),
+    // jump if acc is less than, greater than or equal to zero
+    Jlz(i32),
+    Jgz(i32),
+    Jez(i32),
+    // acc = r, r = acc, acc += r, acc -= r
+    Ld(u8),
+    St(u8),
+    Add(u8),
+    Sub(u8),
+    Hlt,
+    Out,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operand {
+    None,
+    // signed number written with a sign, "+3" or "-3"
+    Imm,
+    // like Imm, but relative to the instruction so the assembler accepts a label
+    Offset,
+    // r0 to r7
+    Reg,
+}
+
+pub struct Opcode {
+    pub name: &'static str,
+    pub operand: Operand,
+    build: fn(i32) -> Instruction,
+}
+
+// every opcode the vm knows, a new one needs a row here, a variant with an arm in
+// Instruction::parts and an arm in Program::execute
+pub const OPCODES: &[Opcode] = &[
+    Opcode {
+        name: "nop",
+        operand: Operand::Imm,
+        build: Instruction::Nop,
+    },
+    Opcode {
+        name: "acc",
+        operand: Operand::Imm,
+        build: Instruction::Acc,
+    },
+    Opcode {
+        name: "jmp",
+        operand: Operand::Offset,
+        build: Instruction::Jmp,
+    },
+    Opcode {
+        name: "jlz",
+        operand: Operand::Offset,
+        build: Instruction::Jlz,
+    },
+    Opcode {
+        name: "jgz",
+        operand: Operand::Offset,
+        build: Instruction::Jgz,
+    },
+    Opcode {
+        name: "jez",
+        operand: Operand::Offset,
+        build: Instruction::Jez,
+    },
+    Opcode {
+        name: "ld",
+        operand: Operand::Reg,
+        build: |r| Instruction::Ld(r as u8),
+    },
+    Opcode {
+        name: "st",
+        operand: Operand::Reg,
+        build: |r| Instruction::St(r as u8),
+    },
+    Opcode {
+        name: "add",
+        operand: Operand::Reg,
+        build: |r| Instruction::Add(r as u8),
+    },
+    Opcode {
+        name: "sub",
+        operand: Operand::Reg,
+        build: |r| Instruction::Sub(r as u8),
+    },
+    Opcode {
+        name: "hlt",
+        operand: Operand::None,
+        build: |_| Instruction::Hlt,
+    },
+    Opcode {
+        name: "out",
+        operand: Operand::None,
+        build: |_| Instruction::Out,
+    },
+];
+
+pub fn lookup(name: &str) -> Option<&'static Opcode> {
+    OPCODES.iter().find(|opcode| opcode.name == name)
+}
+
+impl Opcode {
+    // operand is the text after the opcode, None if there is none
+    pub fn decode(&self, operand: Option<&str>) -> Result<Instruction, String> {
+        let n = match (self.operand, operand) {
+            (Operand::None, None) => 0,
+            (Operand::None, Some(arg)) => {
+                return Err(format!("{} takes no argument, got '{}'", self.name, arg))
+            }
+            (_, None) => return Err(format!("{} needs an argument", self.name)),
+            (Operand::Reg, Some(arg)) => match arg.strip_prefix('r').map(str::parse::<usize>) {
+                Some(Ok(r)) if r < REGISTERS && !arg[1..].starts_with('+') => r as i32,
+                _ => {
+                    return Err(format!(
+                        "invalid register '{}', expected r0 to r{}",
+                        arg,
+                        REGISTERS - 1
+                    ))
+                }
+            },
+            (_, Some(arg)) => {
+                if !arg.starts_with(['+', '-']) {
+                    return Err(format!("argument '{}' must start with '+' or '-'", arg));
+                }
+                arg.parse::<i32>()
+                    .map_err(|_| format!("invalid argument '{}'", arg))?
+            }
+        };
+        Ok((self.build)(n))
+    }
+}
+
+impl Instruction {
+    // the opcode name and the raw operand, 0 if the opcode takes none
+    fn parts(self) -> (&'static str, i32) {
+        match self {
+            Instruction::Nop(n) => ("nop", n),
+            Instruction::Acc(n) => ("acc", n),
+            Instruction::Jmp(n) => ("jmp", n),
+            Instruction::Jlz(n) => ("jlz", n),
+            Instruction::Jgz(n) => ("jgz", n),
+            Instruction::Jez(n) => ("jez", n),
+            Instruction::Ld(r) => ("ld", r as i32),
+            Instruction::St(r) => ("st", r as i32),
+            Instruction::Add(r) => ("add", r as i32),
+            Instruction::Sub(r) => ("sub", r as i32),
+            Instruction::Hlt => ("hlt", 0),
+            Instruction::Out => ("out", 0),
+        }
+    }
+
+    pub fn opcode(self) -> &'static str {
+        self.parts().0
+    }
+
+    // the jump offset for jmp and the conditional jumps
+    pub fn offset(self) -> Option<i32> {
+        let (name, n) = self.parts();
+        match lookup(name).map(|opcode| opcode.operand) {
+            Some(Operand::Offset) => Some(n),
+            _ => None,
+        }
+    }
+
+    // nop, acc and jmp, whose control flow does not depend on acc
+    pub fn is_classic(self) -> bool {
+        matches!(
+            self,
+            Instruction::Nop(_) | Instruction::Acc(_) | Instruction::Jmp(_)
+        )
+    }
+
+    // everything but the conditional jumps, where the next pc does not depend on acc
+    pub fn is_acc_independent(self) -> bool {
+        !matches!(
+            self,
+            Instruction::Jlz(_) | Instruction::Jgz(_) | Instruction::Jez(_)
+        )
+    }
+
+    // nop becomes jmp and jmp becomes nop, everything else stays as it is
+    pub fn swapped(self) -> Instruction {
+        match self {
+            Instruction::Nop(n) => Instruction::Jmp(n),
+            Instruction::Jmp(n) => Instruction::Nop(n),
+            other => other,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+// instruction := opcode [" " operand]
+// operand := ("+" | "-") digits | "r" digit
+impl FromStr for Instruction {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        let (name, operand) = match line.find(' ') {
+            Some(i) => (&line[..i], Some(&line[i + 1..])),
+            None => (line, None),
+        };
+        let opcode = lookup(name).ok_or_else(|| format!("unknown opcode '{}'", name))?;
+        opcode.decode(operand)
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, n) = self.parts();
+        match lookup(name).map(|opcode| opcode.operand) {
+            Some(Operand::Reg) => write!(f, "{} r{}", name, n),
+            Some(Operand::None) => write!(f, "{}", name),
+            _ => write!(f, "{} {:+}", name, n),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_instruction() {
+        assert_eq!("nop +0".parse(), Ok(Instruction::Nop(0)));
+        assert_eq!("acc +13".parse(), Ok(Instruction::Acc(13)));
+        assert_eq!("jmp -13".parse(), Ok(Instruction::Jmp(-13)));
+        let invalid = [
+            "nop",
+            "nop 0",
+            "acc +x",
+            "jmp +99999999999",
+            "mul +2",
+            "acc  +1",
+            "ld r8",
+            "ld r+1",
+            "st 1",
+            "hlt +1",
+        ];
+        for line in invalid.iter() {
+            assert!(line.parse::<Instruction>().is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_opcode_table() {
+        // every opcode round trips through its text form
+        for opcode in OPCODES {
+            let operand = match opcode.operand {
+                Operand::None => None,
+                Operand::Reg => Some("r7"),
+                _ => Some("-7"),
+            };
+            let instruction = opcode.decode(operand).unwrap();
+            assert_eq!(instruction.opcode(), opcode.name);
+            assert_eq!(instruction.to_string().parse(), Ok(instruction));
+        }
+        assert_eq!("jez -2".parse::<Instruction>().unwrap().offset(), Some(-2));
+        assert_eq!(Instruction::Nop(3).offset(), None);
+        assert!(Instruction::Acc(1).is_classic());
+        assert!(!Instruction::Hlt.is_classic());
+        assert!(Instruction::Hlt.is_acc_independent());
+        assert!(Instruction::Sub(1).is_acc_independent());
+        assert!(!Instruction::Jez(1).is_acc_independent());
+    }
+
+    #[test]
+    fn test_swapped() {
+        assert_eq!(Instruction::Nop(3).swapped(), Instruction::Jmp(3));
+        assert_eq!(Instruction::Jmp(-3).swapped(), Instruction::Nop(-3));
+        assert_eq!(Instruction::Acc(3).swapped(), Instruction::Acc(3));
+    }
+}
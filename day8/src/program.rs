@@ -0,0 +1,489 @@
+use crate::instruction::{Instruction, REGISTERS};
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    // pc landed exactly one past the last instruction
+    Terminated {
+        acc: i32,
+    },
+    // pc is about to execute an instruction for the second time, trace holds
+    // every instruction executed so far in order
+    InfiniteLoop {
+        pc: usize,
+        acc: i32,
+        trace: Vec<usize>,
+    },
+    // pc jumped before the first or more than one past the last instruction
+    OutOfBounds {
+        pc: i32,
+    },
+    // the instruction at pc would overflow acc or pc, it was not executed
+    Overflow {
+        pc: usize,
+    },
+    // gave up after steps instructions without terminating or looping
+    StepLimit {
+        steps: usize,
+        pc: usize,
+        acc: i32,
+    },
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Terminated { acc } => write!(f, "terminated, acc {}", acc),
+            Outcome::InfiniteLoop { pc, acc, trace } => write!(
+                f,
+                "infinite loop at instruction {} after {} steps, acc {}",
+                pc,
+                trace.len(),
+                acc
+            ),
+            Outcome::OutOfBounds { pc } => write!(f, "pc {} out of bounds", pc),
+            Outcome::Overflow { pc } => write!(f, "overflow at instruction {}", pc),
+            Outcome::StepLimit { steps, pc, acc } => write!(
+                f,
+                "step limit of {} reached at instruction {}, acc {}",
+                steps, pc, acc
+            ),
+        }
+    }
+}
+
+// one bit per instruction
+struct Visited {
+    words: Vec<u64>,
+}
+
+impl Visited {
+    pub fn new(len: usize) -> Visited {
+        Visited {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    // returns false if pc was already visited
+    pub fn insert(&mut self, pc: usize) -> bool {
+        let word = &mut self.words[pc / 64];
+        let bit = 1 << (pc % 64);
+        let new = *word & bit == 0;
+        *word |= bit;
+        new
+    }
+}
+
+// how many instructions a program with conditional jumps runs when no limit is
+// given, every state it passes through is kept to spot loops
+pub const STEP_LIMIT: usize = 1_000_000;
+
+// what one executed instruction changed, enough to take it back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Undo {
+    pub pc: i32,
+    pub acc: i32,
+    // the register st overwrote and its old value
+    pub register: Option<(u8, i32)>,
+    // whether out wrote a value
+    pub output: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pc: i32,
+    acc: i32,
+    registers: [i32; REGISTERS],
+    // set by hlt
+    stopped: bool,
+    // set instead of executing an instruction that would overflow
+    overflow: bool,
+    // every value written by out
+    output: Vec<i32>,
+}
+
+impl Program {
+    pub fn new() -> Program {
+        Program {
+            pc: 0,
+            acc: 0,
+            registers: [0; REGISTERS],
+            stopped: false,
+            overflow: false,
+            output: Vec::new(),
+        }
+    }
+
+    // None if acc or pc would overflow, the program is then left as it was
+    fn next(&mut self, instruction: Instruction) -> Option<()> {
+        let acc = self.acc;
+        let jump_if = |condition: bool, n: i32| if condition { n } else { 1 };
+        let (acc, offset) = match instruction {
+            Instruction::Nop(_) => (acc, 1),
+            Instruction::Acc(n) => (acc.checked_add(n)?, 1),
+            Instruction::Jmp(n) => (acc, n),
+            Instruction::Jlz(n) => (acc, jump_if(acc < 0, n)),
+            Instruction::Jgz(n) => (acc, jump_if(acc > 0, n)),
+            Instruction::Jez(n) => (acc, jump_if(acc == 0, n)),
+            Instruction::Ld(r) => (self.registers[r as usize], 1),
+            Instruction::St(_) => (acc, 1),
+            Instruction::Add(r) => (acc.checked_add(self.registers[r as usize])?, 1),
+            Instruction::Sub(r) => (acc.checked_sub(self.registers[r as usize])?, 1),
+            Instruction::Hlt => (acc, 0),
+            Instruction::Out => (acc, 1),
+        };
+        let pc = self.pc.checked_add(offset)?;
+        match instruction {
+            Instruction::St(r) => self.registers[r as usize] = acc,
+            Instruction::Hlt => self.stopped = true,
+            Instruction::Out => self.output.push(acc),
+            _ => {}
+        }
+        self.acc = acc;
+        self.pc = pc;
+        Some(())
+    }
+
+    fn execute(&mut self, instruction: Instruction) {
+        if self.next(instruction).is_none() {
+            self.overflow = true;
+        }
+    }
+
+    // Some if pc does not point at an instruction or the program stopped
+    pub fn halted(&self, rom: &[Instruction]) -> Option<Outcome> {
+        if self.overflow {
+            Some(Outcome::Overflow {
+                pc: self.pc as usize,
+            })
+        } else if self.stopped || self.pc as usize == rom.len() && self.pc >= 0 {
+            Some(Outcome::Terminated { acc: self.acc })
+        } else if self.pc < 0 || self.pc as usize > rom.len() {
+            Some(Outcome::OutOfBounds { pc: self.pc })
+        } else {
+            None
+        }
+    }
+
+    // executes the instruction at pc, returns what undo needs to take it back, None if
+    // the program had already halted, and the outcome if the program can not continue
+    pub fn step(&mut self, rom: &[Instruction]) -> (Option<Undo>, Option<Outcome>) {
+        if let Some(outcome) = self.halted(rom) {
+            return (None, Some(outcome));
+        }
+        let instruction = rom[self.pc as usize];
+        let undo = Undo {
+            pc: self.pc,
+            acc: self.acc,
+            register: match instruction {
+                Instruction::St(r) => Some((r, self.registers[r as usize])),
+                _ => None,
+            },
+            output: instruction == Instruction::Out,
+        };
+        self.execute(instruction);
+        (Some(undo), self.halted(rom))
+    }
+
+    // takes back the instruction undo was recorded for, undos must be applied newest first
+    pub fn undo(&mut self, undo: Undo) {
+        // an instruction that overflowed changed nothing else
+        if !self.overflow {
+            if let Some((r, value)) = undo.register {
+                self.registers[r as usize] = value;
+            }
+            if undo.output {
+                self.output.pop();
+            }
+        }
+        self.pc = undo.pc;
+        self.acc = undo.acc;
+        self.stopped = false;
+        self.overflow = false;
+    }
+
+    // runs until the program terminates, jumps out of bounds, is about to repeat an
+    // instruction or has executed limit instructions, with conditional jumps the same
+    // instruction may run again with a different acc, so a loop is only reported once
+    // the whole state repeats, and STEP_LIMIT applies if no limit is given
+    pub fn run(&mut self, rom: &[Instruction], limit: Option<usize>) -> Outcome {
+        let independent = rom
+            .iter()
+            .all(|instruction| instruction.is_acc_independent());
+        let limit = match independent {
+            true => limit,
+            false => limit.or(Some(STEP_LIMIT)),
+        };
+        let mut visited = Visited::new(rom.len());
+        let mut states = HashSet::new();
+        let mut trace = Vec::new();
+        loop {
+            if let Some(outcome) = self.halted(rom) {
+                return outcome;
+            }
+            let pc = self.pc as usize;
+            let repeated = match independent {
+                true => !visited.insert(pc),
+                false => !states.insert(self.state()),
+            };
+            if repeated {
+                return Outcome::InfiniteLoop {
+                    pc,
+                    acc: self.acc,
+                    trace,
+                };
+            }
+            if limit.is_some_and(|limit| trace.len() >= limit) {
+                return Outcome::StepLimit {
+                    steps: trace.len(),
+                    pc,
+                    acc: self.acc,
+                };
+            }
+            trace.push(pc);
+            self.execute(rom[pc]);
+        }
+    }
+
+    // everything that decides what the program does next
+    pub fn state(&self) -> (i32, i32, [i32; REGISTERS]) {
+        (self.pc, self.acc, self.registers)
+    }
+
+    pub fn pc(&self) -> i32 {
+        self.pc
+    }
+
+    pub fn get_acc(&self) -> i32 {
+        self.acc
+    }
+
+    pub fn output(&self) -> &[i32] {
+        &self.output
+    }
+
+    pub fn reset(&mut self) {
+        *self = Program::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::{assemble_str, example};
+
+    #[test]
+    fn test_step() {
+        let rom = example();
+        let mut program = Program::new();
+        for _ in 0..7 {
+            assert_eq!(program.step(&rom).1, None);
+        }
+        assert_eq!(program.pc, 1);
+        assert_eq!(program.get_acc(), 5);
+
+        let rom = [Instruction::Acc(2)];
+        let mut program = Program::new();
+        assert_eq!(program.step(&rom).1, Some(Outcome::Terminated { acc: 2 }));
+        assert_eq!(program.step(&rom).1, Some(Outcome::Terminated { acc: 2 }));
+    }
+
+    #[test]
+    fn test_run() {
+        let mut program = Program::new();
+        assert_eq!(
+            program.run(&example(), None),
+            Outcome::InfiniteLoop {
+                pc: 1,
+                acc: 5,
+                trace: vec![0, 1, 2, 6, 7, 3, 4]
+            }
+        );
+
+        let mut fixed = example();
+        fixed[7] = fixed[7].swapped();
+        program.reset();
+        assert_eq!(program.run(&fixed, None), Outcome::Terminated { acc: 8 });
+        program.reset();
+        assert_eq!(
+            program.run(&fixed, Some(3)),
+            Outcome::StepLimit {
+                steps: 3,
+                pc: 6,
+                acc: 1
+            }
+        );
+
+        program.reset();
+        assert_eq!(
+            program.run(&assemble_str("nop +0\njmp -2"), None),
+            Outcome::OutOfBounds { pc: -1 }
+        );
+        program.reset();
+        assert_eq!(
+            program.run(&assemble_str("jmp +3\nnop +0"), None),
+            Outcome::OutOfBounds { pc: 3 }
+        );
+        program.reset();
+        assert_eq!(program.run(&[], None), Outcome::Terminated { acc: 0 });
+    }
+
+    #[test]
+    fn test_long_loop() {
+        // one big loop, a Vec history would make this quadratic
+        let mut rom = vec![Instruction::Acc(1); 200_000];
+        rom.push(Instruction::Jmp(-200_000));
+        let mut program = Program::new();
+        match program.run(&rom, None) {
+            Outcome::InfiniteLoop { pc, acc, trace } => {
+                assert_eq!((pc, acc, trace.len()), (0, 200_000, 200_001));
+            }
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn test_extended() {
+        let rom = assemble_str(
+            "
+        acc +3
+loop:   jez done
+        out
+        st r0
+        ld r1
+        acc -1
+        add r0
+        jmp loop
+done:   hlt
+        acc +100",
+        );
+        let mut program = Program::new();
+        assert_eq!(program.run(&rom, None), Outcome::Terminated { acc: 0 });
+        assert_eq!(program.output(), &[3, 2, 1]);
+
+        // revisits jlz with a different acc each time, then loops for real
+        let rom = assemble_str(
+            "acc -2
+jlz +2
+hlt
+acc +1
+jmp -3
+",
+        );
+        program.reset();
+        assert_eq!(program.run(&rom, None), Outcome::Terminated { acc: 0 });
+        let rom = assemble_str(
+            "st r2
+sub r2
+jgz -2
+jmp -3
+",
+        );
+        program.reset();
+        match program.run(&rom, None) {
+            Outcome::InfiniteLoop { pc: 0, acc: 0, .. } => {}
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn test_loop_without_conditional_jumps() {
+        // acc keeps growing, but pc 0 running twice already proves the loop
+        let mut program = Program::new();
+        assert_eq!(
+            program.run(&assemble_str("acc +1\nout\njmp -2"), None),
+            Outcome::InfiniteLoop {
+                pc: 0,
+                acc: 1,
+                trace: vec![0, 1, 2]
+            }
+        );
+        assert_eq!(program.output(), &[1]);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut program = Program::new();
+        assert_eq!(
+            program.run(&assemble_str("acc +2147483647\nacc +1"), None),
+            Outcome::Overflow { pc: 1 }
+        );
+        assert_eq!(program.get_acc(), i32::MAX);
+        assert_eq!(
+            program.step(&assemble_str("nop +0")).1,
+            Some(Outcome::Overflow { pc: 1 })
+        );
+
+        for source in [
+            "acc -2147483647\nst r0\nacc +1\nsub r0\nsub r0",
+            "nop +0\njmp +2147483647",
+        ] {
+            program.reset();
+            match program.run(&assemble_str(source), None) {
+                Outcome::Overflow { .. } => {}
+                outcome => panic!("unexpected outcome {:?}", outcome),
+            }
+        }
+        // ld replaces acc, it does not add to it
+        program.reset();
+        assert_eq!(
+            program.run(
+                &assemble_str("acc -2147483647\nst r0\nacc +2147483647\nacc +2147483647\nld r0"),
+                None
+            ),
+            Outcome::Terminated { acc: -2147483647 }
+        );
+    }
+
+    #[test]
+    fn test_undo() {
+        let rom = assemble_str("acc +4\nst r1\nacc +1\nout\nst r1\nhlt");
+        let mut program = Program::new();
+        let mut history = Vec::new();
+        let mut before = Vec::new();
+        loop {
+            before.push(program.clone());
+            let (undo, outcome) = program.step(&rom);
+            history.extend(undo);
+            if outcome.is_some() {
+                break;
+            }
+        }
+        assert_eq!(history.len(), 6);
+        assert_eq!(program.output(), &[5]);
+        assert_eq!(history[4].register, Some((1, 4)));
+        while let Some(undo) = history.pop() {
+            program.undo(undo);
+            assert_eq!(program, before[history.len()]);
+        }
+
+        let rom = [Instruction::Acc(i32::MAX), Instruction::Acc(1)];
+        let mut program = Program::new();
+        let (first, _) = program.step(&rom);
+        let (second, outcome) = program.step(&rom);
+        assert_eq!(outcome, Some(Outcome::Overflow { pc: 1 }));
+        assert_eq!(
+            program.step(&rom),
+            (None, Some(Outcome::Overflow { pc: 1 }))
+        );
+        program.undo(second.unwrap());
+        program.undo(first.unwrap());
+        assert_eq!(program, Program::new());
+    }
+
+    #[test]
+    fn test_default_limit() {
+        // acc keeps growing, so no state repeats before acc would overflow
+        let mut program = Program::new();
+        match program.run(&assemble_str("acc +1\njgz -1"), None) {
+            Outcome::StepLimit { steps, .. } => assert_eq!(steps, STEP_LIMIT),
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+        program.reset();
+        match program.run(&assemble_str("acc +1\njgz -1"), Some(10)) {
+            Outcome::StepLimit { steps, .. } => assert_eq!(steps, 10),
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+}
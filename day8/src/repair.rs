@@ -0,0 +1,191 @@
+use crate::instruction::Instruction;
+use crate::program::{Outcome, Program};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub struct Repair {
+    pub index: usize,
+    pub before: Instruction,
+    pub after: Instruction,
+    // of the repaired program when it terminates
+    pub acc: i32,
+}
+
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "instruction {}: {} -> {}, acc {}",
+            self.index, self.before, self.after, self.acc
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RepairError {
+    AlreadyTerminates {
+        acc: i32,
+    },
+    Unrepairable,
+    // only boot code made of nop, acc and jmp can be repaired
+    Unsupported {
+        index: usize,
+        instruction: Instruction,
+    },
+}
+
+impl fmt::Display for RepairError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepairError::AlreadyTerminates { acc } => {
+                write!(f, "program already terminates, acc {}", acc)
+            }
+            RepairError::Unrepairable => write!(f, "no single nop/jmp swap terminates"),
+            RepairError::Unsupported { index, instruction } => write!(
+                f,
+                "instruction {}: can not repair programs using {}",
+                index,
+                instruction.opcode()
+            ),
+        }
+    }
+}
+
+impl Error for RepairError {}
+
+// where pc goes after executing instruction i
+fn target(i: usize, instruction: Instruction) -> i64 {
+    match instruction {
+        Instruction::Jmp(n) => i as i64 + n as i64,
+        _ => i as i64 + 1,
+    }
+}
+
+// reaches_end[i] is true if execution starting at i terminates, found by walking
+// the edges backwards from the end so every instruction is looked at once
+fn reaches_end(rom: &[Instruction]) -> Vec<bool> {
+    let mut sources = vec![Vec::new(); rom.len() + 1];
+    for (i, instruction) in rom.iter().enumerate() {
+        let t = target(i, *instruction);
+        if t >= 0 && t as usize <= rom.len() {
+            sources[t as usize].push(i);
+        }
+    }
+    let mut reaches = vec![false; rom.len() + 1];
+    reaches[rom.len()] = true;
+    let mut stack = vec![rom.len()];
+    while let Some(i) = stack.pop() {
+        for source in &sources[i] {
+            if !reaches[*source] {
+                reaches[*source] = true;
+                stack.push(*source);
+            }
+        }
+    }
+    reaches
+}
+
+// finds the nop or jmp on the original execution path whose swap lands in an
+// instruction that reaches the end, in time linear in the program length
+pub fn repair(rom: &[Instruction]) -> Result<Repair, RepairError> {
+    if let Some(index) = rom.iter().position(|instruction| !instruction.is_classic()) {
+        return Err(RepairError::Unsupported {
+            index,
+            instruction: rom[index],
+        });
+    }
+    if let Outcome::Terminated { acc } = Program::new().run(rom, None) {
+        return Err(RepairError::AlreadyTerminates { acc });
+    }
+    // the original path up to the first repeated instruction or the jump out of bounds
+    let mut path = Vec::new();
+    let mut visited = vec![false; rom.len()];
+    let mut pc = 0;
+    while pc >= 0 && (pc as usize) < rom.len() && !visited[pc as usize] {
+        visited[pc as usize] = true;
+        path.push(pc as usize);
+        pc = target(pc as usize, rom[pc as usize]);
+    }
+    let reaches = reaches_end(rom);
+    // the swapped instruction can not be on the path from its new target, otherwise
+    // the original program would have terminated
+    for index in path {
+        let before = rom[index];
+        let after = before.swapped();
+        if after == before {
+            continue;
+        }
+        let t = target(index, after);
+        if t >= 0 && t as usize <= rom.len() && reaches[t as usize] {
+            let mut fixed = rom.to_vec();
+            fixed[index] = after;
+            return match Program::new().run(&fixed, None) {
+                Outcome::Terminated { acc } => Ok(Repair {
+                    index,
+                    before,
+                    after,
+                    acc,
+                }),
+                _ => Err(RepairError::Unrepairable),
+            };
+        }
+    }
+    Err(RepairError::Unrepairable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::{assemble_str, example};
+
+    #[test]
+    fn test_repair() {
+        assert_eq!(
+            repair(&example()),
+            Ok(Repair {
+                index: 7,
+                before: Instruction::Jmp(-4),
+                after: Instruction::Nop(-4),
+                acc: 8
+            })
+        );
+        assert_eq!(
+            repair(&assemble_str("acc +1\nnop +0")),
+            Err(RepairError::AlreadyTerminates { acc: 1 })
+        );
+        assert_eq!(
+            repair(&assemble_str("jmp +0\njmp -1")),
+            Err(RepairError::Unrepairable)
+        );
+        assert_eq!(
+            repair(&assemble_str("nop +0\njez -1"))
+                .unwrap_err()
+                .to_string(),
+            "instruction 1: can not repair programs using jez"
+        );
+        // jumps out of bounds unless the nop becomes a jmp over it
+        assert_eq!(
+            repair(&assemble_str("nop +2\njmp -5\nacc +3")).map(|r| (r.index, r.acc)),
+            Ok((0, 3))
+        );
+    }
+
+    #[test]
+    fn test_reaches_end() {
+        let reaches = reaches_end(&example());
+        assert_eq!(
+            reaches,
+            vec![false, false, false, false, false, false, false, false, true, true]
+        );
+    }
+
+    #[test]
+    fn test_repair_display() {
+        let repaired = repair(&example()).unwrap();
+        assert_eq!(
+            repaired.to_string(),
+            "instruction 7: jmp -4 -> nop -4, acc 8"
+        );
+    }
+}
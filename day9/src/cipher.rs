@@ -0,0 +1,109 @@
+use std::collections::{HashMap, VecDeque};
+
+// checks each number against the sums of pairs from the preamble numbers before it,
+// the window is kept as a queue for order and a multiset for lookups
+pub struct XmasCipher {
+    preamble: usize,
+    window: VecDeque<i64>,
+    counts: HashMap<i64, usize>,
+    // how many numbers were pushed so far
+    index: usize,
+}
+
+impl XmasCipher {
+    pub fn new(preamble: usize) -> XmasCipher {
+        XmasCipher {
+            preamble,
+            window: VecDeque::with_capacity(preamble + 1),
+            counts: HashMap::with_capacity(preamble + 1),
+            index: 0,
+        }
+    }
+
+    // true if two numbers at different positions in the window add up to n, O(preamble)
+    pub fn is_sum(&self, n: i64) -> bool {
+        self.window.iter().any(|x| {
+            let y = n - x;
+            match self.counts.get(&y) {
+                Some(count) if y == *x => *count >= 2,
+                Some(_) => true,
+                None => false,
+            }
+        })
+    }
+
+    // feeds the next number, returns whether it is valid, None while it is part
+    // of the preamble
+    pub fn push(&mut self, n: i64) -> Option<bool> {
+        let valid = match self.window.len() == self.preamble {
+            true => Some(self.is_sum(n)),
+            false => None,
+        };
+        self.window.push_back(n);
+        *self.counts.entry(n).or_insert(0) += 1;
+        if self.window.len() > self.preamble {
+            let old = self.window.pop_front().unwrap();
+            if let Some(count) = self.counts.get_mut(&old) {
+                *count -= 1;
+                if *count == 0 {
+                    self.counts.remove(&old);
+                }
+            }
+        }
+        self.index += 1;
+        valid
+    }
+
+    // every number that is not a sum of two of the preamble numbers before it, with its index
+    pub fn invalid(&mut self, list: &[i64]) -> Vec<(usize, i64)> {
+        let mut invalid = Vec::new();
+        for n in list {
+            let index = self.index;
+            if self.push(*n) == Some(false) {
+                invalid.push((index, *n));
+            }
+        }
+        invalid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TEST;
+
+    #[test]
+    fn test_is_sum() {
+        let mut cipher = XmasCipher::new(3);
+        for n in [1, 2, 3].iter() {
+            assert_eq!(cipher.push(*n), None);
+        }
+        assert!(cipher.is_sum(3));
+        assert!(cipher.is_sum(5));
+        assert!(cipher.is_sum(4));
+        assert!(!cipher.is_sum(6));
+        assert!(!cipher.is_sum(2));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(XmasCipher::new(5).invalid(&TEST), vec![(14, 127)]);
+        // with a shorter window more numbers fail
+        let invalid = XmasCipher::new(2).invalid(&TEST);
+        assert_eq!(invalid.len(), 18);
+        assert_eq!(invalid[0], (2, 15));
+    }
+
+    #[test]
+    fn test_window() {
+        // the same value twice in the window is a valid pair, once it is not
+        let mut cipher = XmasCipher::new(2);
+        cipher.push(5);
+        cipher.push(5);
+        assert_eq!(cipher.push(10), Some(true));
+        assert_eq!(cipher.push(10), Some(false));
+        assert_eq!(cipher.push(20), Some(true));
+        assert_eq!(cipher.window, vec![10, 20]);
+        assert_eq!(cipher.counts.len(), 2);
+    }
+}
@@ -1,9 +1,49 @@
+use cipher::XmasCipher;
+use ranges::find_ranges;
 use std::env;
+use std::error::Error;
 use std::fs::File;
 use std::io::{self, BufRead, BufReader};
 
-fn main() -> Result<(), io::Error> {
+mod cipher;
+mod ranges;
+mod stream;
+
+const PREAMBLE: usize = 25;
+
+// the example from the puzzle, with a preamble of 5
+#[cfg(test)]
+const TEST: [i64; 20] = [
+    35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102, 117, 150, 182, 127, 219, 299, 277, 309, 576,
+];
+
+fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = env::args().collect();
+    let preamble = match args.get(3) {
+        Some(n) => n.parse::<usize>()?,
+        None => PREAMBLE,
+    };
+    if &args[2] == "stream" {
+        // <file or - for stdin> stream [preamble] [target] [--negatives]
+        let target = match args.get(4) {
+            Some(n) => Some(n.parse::<i64>()?),
+            None => None,
+        };
+        let negatives = match args.get(5).map(String::as_str) {
+            Some("--negatives") => true,
+            Some(arg) => return Err(format!("unknown stream option '{}'", arg).into()),
+            None => false,
+        };
+        let stdout = io::stdout();
+        if &args[1] == "-" {
+            let stdin = io::stdin();
+            stream::validate(stdin.lock(), stdout.lock(), preamble, target, negatives)?;
+        } else {
+            let reader = BufReader::new(File::open(&args[1])?);
+            stream::validate(reader, stdout.lock(), preamble, target, negatives)?;
+        }
+        return Ok(());
+    }
     let reader = BufReader::new(File::open(&args[1]).expect("File::open failed"));
     let list = reader
         .lines()
@@ -14,67 +54,27 @@ fn main() -> Result<(), io::Error> {
         })
         .collect::<Vec<i64>>();
 
-    let mut sum_hit = 0;
-    for i in 25..list.len() {
-        let preamble = &list[i - 25..i];
-        if !find_sum(preamble, list[i]) {
-            sum_hit = list[i];
-            break;
-        }
-    }
+    let invalid = XmasCipher::new(preamble).invalid(&list);
+    let sum_hit = invalid.first().map_or(0, |(_, n)| *n);
     if &args[2] == "1" {
         println!("{}", sum_hit);
     } else if &args[2] == "2" {
-        let series = find_continuous_sum(&list, sum_hit);
-        if let Some(mut series) = series {
-            series.sort();
-            println!("{}", series[0] + series[series.len() - 1])
+        if let Some(range) = find_ranges(&list, sum_hit).first() {
+            let series = &list[range.clone()];
+            let min = series.iter().min().unwrap();
+            let max = series.iter().max().unwrap();
+            println!("{}", min + max)
         }
-    }
-
-    Ok(())
-}
-
-fn find_sum(list: &[i64], n: i64) -> bool {
-    for i in 0..list.len() - 1 {
-        for j in i + 1..list.len() {
-            if list[i] + list[j] == n {
-                return true;
-            }
-        }
-    }
-    return false;
-}
-
-fn find_continuous_sum(list: &[i64], target: i64) -> Option<Vec<i64>> {
-    let mut series = Vec::<i64>::new();
-    for i in 0..list.len() - 1 {
-        let mut sum = list[i];
-        series.push(list[i]);
-        for j in i + 1..list.len() {
-            if sum + list[j] > target {
-                break;
-            }
-            sum += list[j];
-            series.push(list[j]);
+    } else if &args[2] == "ranges" {
+        // every range summing to the first invalid number
+        for range in find_ranges(&list, sum_hit) {
+            println!("{}..{}", range.start, range.end);
         }
-        if sum == target {
-            return Some(series);
+    } else if &args[2] == "invalid" {
+        for (index, n) in invalid {
+            println!("{} {}", index, n);
         }
-        series.clear();
     }
-    return None;
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    #[test]
-    fn test_stuff() {
-        let list = vec![1, 2, 3];
-        assert_eq!(find_sum(&list, 3), true);
-        assert_eq!(find_sum(&list, 5), true);
-        assert_eq!(find_sum(&list, 4), true);
-        assert_eq!(find_sum(&list, 6), false);
-    }
+    Ok(())
 }
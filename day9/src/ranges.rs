@@ -0,0 +1,203 @@
+use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
+
+// the numbers since the last possible start of a range summing to target, for
+// numbers that are not negative the window never sums to more than target, so it
+// only holds as many numbers as it takes to get there
+pub struct RangeWindow {
+    target: i64,
+    window: VecDeque<i64>,
+    sum: i64,
+    // index of the first number in the window
+    start: usize,
+}
+
+impl RangeWindow {
+    pub fn new(target: i64) -> RangeWindow {
+        RangeWindow {
+            target,
+            window: VecDeque::new(),
+            sum: 0,
+            start: 0,
+        }
+    }
+
+    // feeds the next number, returns every range of at least two numbers that ends
+    // with it and sums to target
+    pub fn push(&mut self, n: i64) -> Result<Vec<Range<usize>>, String> {
+        if n < 0 {
+            return Err(format!("negative number {} in a sliding window", n));
+        }
+        self.window.push_back(n);
+        self.sum += n;
+        while self.sum > self.target {
+            match self.window.pop_front() {
+                Some(first) => self.sum -= first,
+                None => break,
+            }
+            self.start += 1;
+        }
+        let end = self.start + self.window.len();
+        let mut ranges = Vec::new();
+        if self.sum == self.target {
+            // leading zeros can be dropped without changing the sum
+            for (i, first) in self.window.iter().enumerate() {
+                if self.window.len() - i < 2 {
+                    break;
+                }
+                ranges.push(self.start + i..end);
+                if *first != 0 {
+                    break;
+                }
+            }
+        }
+        Ok(ranges)
+    }
+}
+
+// every range of at least two numbers summing to target, for lists without
+// negative numbers, the window only ever grows on the right and shrinks on the left
+fn two_pointer(list: &[i64], target: i64) -> Vec<Range<usize>> {
+    let mut window = RangeWindow::new(target);
+    let mut ranges = list
+        .iter()
+        .flat_map(|n| window.push(*n).expect("two_pointer: negative number"))
+        .collect::<Vec<Range<usize>>>();
+    ranges.sort_by_key(|range| (range.start, range.end));
+    ranges
+}
+
+// the prefix sums of every number so far, a range start..end sums to target when
+// prefix(end) - prefix(start) == target, works with negative numbers but has to
+// keep one entry per number since any earlier start may still begin a range
+pub struct PrefixSums {
+    target: i64,
+    // prefix sum -> every start with that prefix sum
+    starts: HashMap<i64, Vec<usize>>,
+    // prefix sum before the previous number
+    before: i64,
+    prefix: i64,
+    // how many numbers were pushed so far
+    index: usize,
+}
+
+impl PrefixSums {
+    pub fn new(target: i64) -> PrefixSums {
+        PrefixSums {
+            target,
+            starts: HashMap::new(),
+            before: 0,
+            prefix: 0,
+            index: 0,
+        }
+    }
+
+    // feeds the next number, returns every range of at least two numbers that ends
+    // with it and sums to target, in order of their start
+    pub fn push(&mut self, n: i64) -> Vec<Range<usize>> {
+        let i = self.index;
+        // start i - 1 only becomes usable once the range has two numbers
+        if i > 0 {
+            self.starts.entry(self.before).or_default().push(i - 1);
+        }
+        self.before = self.prefix;
+        self.prefix += n;
+        self.index += 1;
+        match self.starts.get(&(self.prefix - self.target)) {
+            Some(found) => found.iter().map(|start| *start..i + 1).collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+// every range of at least two numbers summing to target, for any list
+pub fn prefix_sum(list: &[i64], target: i64) -> Vec<Range<usize>> {
+    let mut sums = PrefixSums::new(target);
+    let mut ranges = list
+        .iter()
+        .flat_map(|n| sums.push(*n))
+        .collect::<Vec<Range<usize>>>();
+    ranges.sort_by_key(|range| (range.start, range.end));
+    ranges
+}
+
+// picks the two pointer search unless the list holds negative numbers
+pub fn find_ranges(list: &[i64], target: i64) -> Vec<Range<usize>> {
+    match list.iter().any(|n| *n < 0) {
+        true => prefix_sum(list, target),
+        false => two_pointer(list, target),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TEST;
+
+    // checks every range one by one
+    fn brute_force(list: &[i64], target: i64) -> Vec<Range<usize>> {
+        let mut ranges = Vec::new();
+        for start in 0..list.len() {
+            for end in start + 2..=list.len() {
+                if list[start..end].iter().sum::<i64>() == target {
+                    ranges.push(start..end);
+                }
+            }
+        }
+        ranges
+    }
+
+    #[test]
+    fn test_find_ranges() {
+        assert_eq!(find_ranges(&TEST, 127), vec![2..6]);
+        assert_eq!(find_ranges(&TEST, 182), vec![0..6, 6..9]);
+        // 15 on its own is not a range
+        assert_eq!(two_pointer(&TEST, 15), vec![]);
+        assert_eq!(prefix_sum(&TEST, 15), vec![]);
+        assert_eq!(find_ranges(&[1, 2, 3, 3], 6), vec![0..3, 2..4]);
+    }
+
+    #[test]
+    fn test_zeros_and_negatives() {
+        let zeros = [0, 0, 3, 0, 2, 0];
+        assert_eq!(two_pointer(&zeros, 3), vec![0..3, 0..4, 1..3, 1..4, 2..4]);
+        assert_eq!(two_pointer(&zeros, 3), brute_force(&zeros, 3));
+        assert_eq!(two_pointer(&zeros, 0), vec![0..2]);
+
+        let negatives = [5, -2, 4, -7, 10, 3, -3];
+        assert_eq!(find_ranges(&negatives, 3), vec![0..2, 3..5, 3..7]);
+        assert_eq!(find_ranges(&negatives, 3), brute_force(&negatives, 3));
+        assert_eq!(find_ranges(&negatives, 0), vec![0..4, 5..7]);
+    }
+
+    #[test]
+    fn test_range_window() {
+        let mut window = RangeWindow::new(127);
+        let mut found = Vec::new();
+        for n in TEST.iter() {
+            found.extend(window.push(*n).unwrap());
+            assert!(window.sum <= 127);
+        }
+        assert_eq!(found, vec![2..6]);
+        assert!(window.push(-1).is_err());
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut seed = 17u64;
+        let mut next = || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as i64 % 7
+        };
+        let positive = (0..200).map(|_| next()).collect::<Vec<i64>>();
+        let mixed = (0..200).map(|_| next() - 3).collect::<Vec<i64>>();
+        for target in -3..12 {
+            let expected = brute_force(&positive, target);
+            assert_eq!(two_pointer(&positive, target), expected);
+            assert_eq!(prefix_sum(&positive, target), expected);
+            assert_eq!(prefix_sum(&mixed, target), brute_force(&mixed, target));
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::cipher::XmasCipher;
+use crate::ranges::{PrefixSums, RangeWindow};
+use std::error::Error;
+use std::io::{BufRead, Write};
+use std::ops::Range;
+
+enum RangeFinder {
+    // bounded memory, stops at the first negative number
+    Window(RangeWindow),
+    // one entry per number, but handles negative numbers
+    Sums(PrefixSums),
+}
+
+impl RangeFinder {
+    fn push(&mut self, n: i64) -> Result<Vec<Range<usize>>, String> {
+        match self {
+            RangeFinder::Window(window) => window
+                .push(n)
+                .map_err(|message| format!("{}, use --negatives to allow it", message)),
+            RangeFinder::Sums(sums) => Ok(sums.push(n)),
+        }
+    }
+}
+
+// reports invalid numbers, and ranges summing to target if given, as soon as they are
+// found, memory is bounded unless negatives allows negative numbers in the ranges
+pub fn validate<R: BufRead, W: Write>(
+    reader: R,
+    mut out: W,
+    preamble: usize,
+    target: Option<i64>,
+    negatives: bool,
+) -> Result<usize, Box<dyn Error>> {
+    let mut cipher = XmasCipher::new(preamble);
+    let mut finder = target.map(|target| match negatives {
+        true => RangeFinder::Sums(PrefixSums::new(target)),
+        false => RangeFinder::Window(RangeWindow::new(target)),
+    });
+    let mut invalid = 0;
+    let mut index = 0;
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let n = line
+            .trim()
+            .parse::<i64>()
+            .map_err(|_| format!("line {}: invalid number '{}'", i + 1, line))?;
+        if cipher.push(n) == Some(false) {
+            invalid += 1;
+            writeln!(out, "invalid {} {}", index, n)?;
+            out.flush()?;
+        }
+        if let Some(finder) = finder.as_mut() {
+            let ranges = finder
+                .push(n)
+                .map_err(|message| format!("line {}: {}", i + 1, message))?;
+            for range in ranges {
+                writeln!(out, "range {}..{}", range.start, range.end)?;
+                out.flush()?;
+            }
+        }
+        index += 1;
+    }
+    Ok(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TEST;
+
+    // the example numbers one per line
+    fn input() -> String {
+        TEST.iter().map(|n| format!("{}\n", n)).collect()
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut out = Vec::new();
+        assert_eq!(
+            validate(input().as_bytes(), &mut out, 5, Some(127), false).unwrap(),
+            1
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "range 2..6\ninvalid 14 127\n"
+        );
+
+        let mut out = Vec::new();
+        let input = input().replace("\n", "\n\n");
+        assert_eq!(
+            validate(input.as_bytes(), &mut out, 5, None, false).unwrap(),
+            1
+        );
+        assert_eq!(String::from_utf8(out).unwrap(), "invalid 14 127\n");
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = validate("1\n2\nx\n".as_bytes(), Vec::new(), 2, None, false).unwrap_err();
+        assert_eq!(error.to_string(), "line 3: invalid number 'x'");
+        let error = validate("1\n-2\n".as_bytes(), Vec::new(), 2, Some(3), false).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 2: negative number -2 in a sliding window, use --negatives to allow it"
+        );
+    }
+
+    #[test]
+    fn test_negatives() {
+        // with negatives -2 does not stop the stream, and -7 + 10 + 3 + -3 is found after it
+        let mut out = Vec::new();
+        let input = "5\n-2\n4\n-7\n10\n3\n-3\n";
+        assert_eq!(
+            validate(input.as_bytes(), &mut out, 2, Some(3), true).unwrap(),
+            4
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "range 0..2\ninvalid 2 4\ninvalid 3 -7\ninvalid 4 10\nrange 3..5\ninvalid 6 -3\nrange 3..7\n"
+        );
+    }
+}